@@ -1,7 +1,7 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use clap::Parser;
-use fps_core::{evaluator, parser, tokenizer};
-
+use fps_core::query::{self, Options};
+use fps_core::series::ExpansionPoint;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -9,16 +9,60 @@ struct Args {
     /// The expression to parse
     expression: String,
 
-    /// Maximum degree of the resulting power series (default: 5)
+    /// Maximum degree of the resulting power series (default: 5). Exact
+    /// rational coefficients grow with the degree: expansions such as exp(x)
+    /// take about a second at degree 500, ten times as long per doubling
+    /// beyond, and do not reach degree 10000. With --modulus, degree 10000
+    /// takes well under a second
     #[arg(short, long)]
     maxdeg: Option<usize>,
+
+    /// Series variable (default: the only variable in the expression, or x)
+    #[arg(long = "var", conflicts_with = "bounds")]
+    variable: Option<char>,
+
+    /// Point to expand around: a rational number such as 1 or -1/2, or inf
+    /// (default: 0)
+    #[arg(long, conflicts_with = "bounds", allow_hyphen_values = true)]
+    at: Option<ExpansionPoint>,
+
+    /// Print a rational result in closed form, e.g. 1/(1 - x - x^2), instead
+    /// of as a series
+    #[arg(long, conflicts_with_all = ["bounds", "at"])]
+    closed_form: bool,
+
+    /// Per-variable degree bounds for multivariate expressions, in
+    /// alphabetical variable order (e.g. --bounds 3,2). Without this,
+    /// multivariate series are truncated by total degree
+    #[arg(long, value_delimiter = ',')]
+    bounds: Option<Vec<usize>>,
+
+    /// Compute with coefficients modulo this prime, below 2^32 (e.g.
+    /// --modulus 998244353)
+    #[arg(long, conflicts_with_all = ["bounds", "at", "closed_form"])]
+    modulus: Option<u64>,
+
+    /// Compute modulo several primes and recover the exact rationals. This
+    /// pays off at high degree when the coefficients share large
+    /// denominators, as in exp(x), and can be slower otherwise. The output is
+    /// the same as without it
+    #[arg(long, conflicts_with_all = ["bounds", "closed_form", "modulus"])]
+    multimodular: bool,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let tokens = tokenizer::tokenize(&args.expression)?;
-    let ast = parser::parse(&tokens)?;
-    let series = evaluator::evaluate(&ast, args.maxdeg.unwrap_or(5))?;
-    println!("{}", series);
+    let options = Options {
+        max_degree: args.maxdeg.unwrap_or(5),
+        variable: args.variable,
+        point: args.at,
+        closed_form: args.closed_form,
+        bounds: args.bounds,
+        modulus: args.modulus,
+        multimodular: args.multimodular,
+    };
+    let output =
+        query::run(&args.expression, &options).map_err(|e| anyhow!(e.render(&args.expression)))?;
+    println!("{}", output);
     Ok(())
 }
@@ -1,8 +1,12 @@
 use std::process::Command;
 
 fn run_cli(expression: &str) -> String {
+    run_cli_with_args(&[expression, "--maxdeg", "3"])
+}
+
+fn run_cli_with_args(args: &[&str]) -> String {
     let output = Command::new(env!("CARGO_BIN_EXE_fps-cli"))
-        .arg(expression)
+        .args(args)
         .output()
         .expect("failed to run fps-cli");
 
@@ -13,6 +17,20 @@ fn run_cli(expression: &str) -> String {
         .to_string()
 }
 
+fn run_cli_failure(expression: &str) -> String {
+    run_cli_failure_with_args(&[expression])
+}
+
+fn run_cli_failure_with_args(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_fps-cli"))
+        .args(args)
+        .output()
+        .expect("failed to run fps-cli");
+
+    assert!(!output.status.success(), "CLI unexpectedly succeeded");
+    String::from_utf8(output.stderr).expect("stderr should be valid UTF-8")
+}
+
 #[test]
 fn prints_series_for_sin() {
     let output = run_cli("sin(3x)");
@@ -30,3 +48,190 @@ fn prints_series_for_log() {
     let output = run_cli("log(1 + 7x)");
     assert_eq!(output, "7 x - 49/2 x^2 + 343/3 x^3 + O(x^4)");
 }
+
+#[test]
+fn underlines_offending_subexpression() {
+    let stderr = run_cli_failure("1 + log(2 + x)");
+    assert!(
+        stderr.contains("1 | 1 + log(2 + x)\n  |     ^^^^^^^^^^"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+#[test]
+fn honours_explicit_variable() {
+    let output = run_cli_with_args(&["1/(1-t)", "--var", "t", "--maxdeg", "2"]);
+    assert_eq!(output, "1 + t + t^2 + O(t^3)");
+}
+
+#[test]
+fn expands_bivariate_series_by_total_degree() {
+    let output = run_cli_with_args(&["exp(x + y)", "--maxdeg", "2"]);
+    assert_eq!(output, "1 + x + y + 1/2 x^2 + x y + 1/2 y^2 + O((x, y)^3)");
+}
+
+#[test]
+fn expands_bivariate_series_with_bounds() {
+    let output = run_cli_with_args(&["1/(1 - x y)", "--bounds", "2,1"]);
+    assert_eq!(output, "1 + x y + O(x^3, y^2)");
+}
+
+#[test]
+fn prints_extracted_coefficient_as_rational() {
+    let output = run_cli("[x^30] 1/(1-x-x^2)");
+    assert_eq!(output, "1346269");
+
+    let output = run_cli("coeff(exp(x), 4)");
+    assert_eq!(output, "1/24");
+}
+
+#[test]
+fn division_by_x_keeps_requested_degree() {
+    let output = run_cli("(1 - cos(x))/x^2");
+    assert_eq!(output, "1/2 - 1/24 x^2 + O(x^4)");
+}
+
+#[test]
+fn expands_around_given_point() {
+    let output = run_cli_with_args(&["log(x)", "--at", "1", "-m", "3"]);
+    assert_eq!(output, "(x-1) - 1/2 (x-1)^2 + 1/3 (x-1)^3 + O((x-1)^4)");
+
+    let output = run_cli_with_args(&["x/(x^2+1)", "--at", "inf", "-m", "3"]);
+    assert_eq!(output, "1/x - 1/x^3 + O(1/x^4)");
+
+    let output = run_cli_with_args(&["1/x", "--at", "-1/2", "-m", "1"]);
+    assert_eq!(output, "-2 - 4 (x+1/2) + O((x+1/2)^2)");
+}
+
+#[test]
+fn prints_exact_polynomials_without_o_term() {
+    assert_eq!(
+        run_cli("(1+x)^5"),
+        "1 + 5 x + 10 x^2 + 10 x^3 + 5 x^4 + x^5"
+    );
+    assert_eq!(run_cli("(x^3 - 1)/(x - 1)"), "1 + x + x^2");
+    assert_eq!(run_cli("[x^2] (1+x)^4"), "6");
+    assert_eq!(
+        run_cli("(1+x)^5/(1-x)"),
+        "1 + 6 x + 16 x^2 + 26 x^3 + O(x^4)"
+    );
+}
+
+#[test]
+fn expands_large_powers_as_truncated_series() {
+    let output = run_cli_with_args(&["(1+2x)^2000/(1+x)^2000", "-m", "1"]);
+    assert_eq!(output, "1 + 2000 x + O(x^2)");
+
+    let output = run_cli_with_args(&["1/(1-x)^2000 + 1/(1-2x)^2000", "-m", "1"]);
+    assert_eq!(output, "2 + 6000 x + O(x^2)");
+}
+
+#[test]
+fn factors_polynomials_over_the_rationals() {
+    assert_eq!(run_cli("factor(x^4 - 1)"), "(-1 + x) (1 + x) (1 + x^2)");
+    assert_eq!(run_cli("factor(2 - 2x^2)"), "-2 (-1 + x) (1 + x)");
+    assert_eq!(
+        run_cli("let p = 1 - x; factor(p^2 (1 + x + x^2))"),
+        "(-1 + x)^2 (1 + x + x^2)"
+    );
+
+    let stderr = run_cli_failure("factor(1/(1 - x))");
+    assert!(
+        stderr.contains("division does not give a polynomial"),
+        "unexpected stderr: {stderr}"
+    );
+
+    let stderr = run_cli_failure_with_args(&["factor(x^2 - 1)", "--at", "1"]);
+    assert!(
+        stderr.contains("factor(...) cannot be combined with an expansion point"),
+        "unexpected stderr: {stderr}"
+    );
+
+    let stderr = run_cli_failure_with_args(&["factor(x^2 - 1)", "--modulus", "7"]);
+    assert!(
+        stderr.contains("factor(...) cannot be combined with modular arithmetic"),
+        "unexpected stderr: {stderr}"
+    );
+
+    let stderr = run_cli_failure_with_args(&["factor(x^2 - 1)", "--multimodular"]);
+    assert!(
+        stderr.contains("factor(...) cannot be combined with multimodular evaluation"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+#[test]
+fn prints_rational_functions_in_closed_form() {
+    let output = run_cli_with_args(&["1/(1-x) - x/(1-x)^2", "--closed-form"]);
+    assert_eq!(output, "(1 - 2 x)/(1 - 2 x + x^2)");
+
+    let output = run_cli_with_args(&["D(x/(1-x-x^2))", "--closed-form"]);
+    assert_eq!(output, "(1 + x^2)/(1 - 2 x - x^2 + 2 x^3 + x^4)");
+
+    let stderr = run_cli_failure_with_args(&["exp(x)/(1-x)", "--closed-form"]);
+    assert!(
+        stderr.contains("exp does not give a rational function"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+#[test]
+fn extracts_distant_coefficients_of_rational_functions() {
+    let output = run_cli("[x^300] 1/(1-x-x^2)");
+    assert_eq!(
+        output,
+        "359579325206583560961765665172189099052367214309267232255589801"
+    );
+}
+
+#[test]
+fn computes_modulo_a_prime() {
+    let output = run_cli_with_args(&["exp(x)", "-m", "3", "--modulus", "998244353"]);
+    assert_eq!(output, "1 + x + 499122177 x^2 + 166374059 x^3 + O(x^4)");
+
+    let output = run_cli_with_args(&["[x^1000] 1/(1-x-x^2)", "--modulus", "1000000007"]);
+    assert_eq!(output, "107579939");
+
+    let stderr = run_cli_failure_with_args(&["exp(x)", "-m", "8", "--modulus", "7"]);
+    assert!(
+        stderr.contains("7 has no inverse modulo 7"),
+        "unexpected stderr: {stderr}"
+    );
+
+    let stderr = run_cli_failure_with_args(&["x^([x^1] x/2)", "--modulus", "998244353"]);
+    assert!(
+        stderr.contains("Coefficient 499122177 has no rational value"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+#[test]
+fn multimodular_evaluation_prints_exact_series() {
+    for expr in [
+        "exp(x) * log(1 - x)^2",
+        "sqrt(9/4 + x)",
+        "x^([x^1] x/2)",
+        "x^([x^2] exp(x)) + x",
+        "x^(1/([x^1] 2x))",
+        "1/x - 1/x",
+        "sin(x) - sin(x)",
+    ] {
+        assert_eq!(
+            run_cli_with_args(&[expr, "-m", "8", "--multimodular"]),
+            run_cli_with_args(&[expr, "-m", "8"]),
+            "{expr}"
+        );
+    }
+
+    let output = run_cli_with_args(&["[x^30] exp(sin(x))", "-m", "30", "--multimodular"]);
+    assert_eq!(
+        output,
+        "1446188098724255329/3844244345104218241105920000000"
+    );
+
+    let stderr = run_cli_failure_with_args(&["log(2 + x)", "--multimodular"]);
+    assert!(
+        stderr.contains("log requires series with constant term equal to 1"),
+        "unexpected stderr: {stderr}"
+    );
+}
@@ -0,0 +1,240 @@
+//! Coefficient rings for [`Series`](crate::series::Series).
+//!
+//! Series arithmetic only needs [`Coefficient`]; functions that divide by
+//! integers, such as `exp`, `log` and `int`, need a [`Field`]. Both are
+//! implemented for exact rationals, residues modulo a prime
+//! ([`ModInt`](crate::modint::ModInt)) and `f64`, and can be implemented
+//! for other types to evaluate expressions over them.
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, One, Signed, ToPrimitive, Zero};
+use std::fmt;
+
+use crate::dense;
+use crate::evaluator::EvalError;
+use crate::series::rational_power;
+
+/// A commutative ring of series coefficients.
+pub trait Coefficient: Clone + PartialEq + fmt::Debug + fmt::Display {
+    /// Data shared by all coefficients of a series, such as a modulus,
+    /// needed to build constants; `()` for most rings.
+    type Context: Clone + PartialEq + fmt::Debug;
+
+    fn from_integer(value: &BigInt, context: &Self::Context) -> Self;
+
+    fn zero(context: &Self::Context) -> Self {
+        Self::from_integer(&BigInt::zero(), context)
+    }
+
+    fn one(context: &Self::Context) -> Self {
+        Self::from_integer(&BigInt::one(), context)
+    }
+
+    fn is_zero(&self) -> bool;
+    fn is_one(&self) -> bool;
+
+    /// Whether the coefficient is shown as `-c` for the coefficient `c`
+    /// returned by [`Coefficient::neg`].
+    fn is_negative(&self) -> bool {
+        false
+    }
+
+    fn add(&self, other: &Self) -> Self;
+    fn neg(&self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+
+    fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    /// A rational with the value of the coefficient, used where it feeds
+    /// back into an expression, as with `[x^n]`; `None` if there is none.
+    fn to_rational(&self) -> Option<BigRational>;
+
+    /// First `len` coefficients of the product of the polynomials `a` and
+    /// `b`. Rings with a faster method than the default schoolbook one
+    /// override this.
+    fn mul_truncated(a: &[Self], b: &[Self], len: usize, context: &Self::Context) -> Vec<Self> {
+        let mut result = vec![Self::zero(context); len];
+        for (i, x) in a.iter().enumerate().take(len) {
+            if x.is_zero() {
+                continue;
+            }
+            for (j, y) in b.iter().enumerate().take(len - i) {
+                result[i + j] = result[i + j].add(&x.mul(y));
+            }
+        }
+        result
+    }
+}
+
+/// A field of series coefficients, for division and the functions built on
+/// it.
+pub trait Field: Coefficient {
+    /// The value of a rational literal, failing if its denominator has no
+    /// inverse.
+    fn from_rational(value: &BigRational, context: &Self::Context) -> Result<Self, EvalError>;
+
+    /// Multiplicative inverse of a non-zero coefficient.
+    fn inverse(&self) -> Result<Self, EvalError>;
+
+    /// `1/n`, failing if `n` is zero in the field.
+    fn reciprocal(n: i64, context: &Self::Context) -> Result<Self, EvalError> {
+        Self::from_integer(&BigInt::from(n), context).inverse()
+    }
+
+    /// Power with a non-integer rational exponent, for the first coefficient
+    /// of a series raised to it; fails if the field has no such power.
+    fn powr(&self, exponent: &BigRational) -> Result<Self, EvalError>;
+}
+
+impl Coefficient for BigRational {
+    type Context = ();
+
+    fn from_integer(value: &BigInt, _context: &()) -> Self {
+        BigRational::from_integer(value.clone())
+    }
+
+    fn zero(_context: &()) -> Self {
+        Zero::zero()
+    }
+
+    fn one(_context: &()) -> Self {
+        One::one()
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+
+    fn is_one(&self) -> bool {
+        One::is_one(self)
+    }
+
+    fn is_negative(&self) -> bool {
+        Signed::is_negative(self)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn neg(&self) -> Self {
+        -self
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self * other
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self - other
+    }
+
+    fn to_rational(&self) -> Option<BigRational> {
+        Some(self.clone())
+    }
+
+    /// Karatsuba short product over the integers, see [`dense::mul_rational`].
+    fn mul_truncated(a: &[Self], b: &[Self], len: usize, _context: &()) -> Vec<Self> {
+        dense::mul_rational(a, b, len)
+    }
+}
+
+impl Field for BigRational {
+    fn from_rational(value: &BigRational, _context: &()) -> Result<Self, EvalError> {
+        Ok(value.clone())
+    }
+
+    fn inverse(&self) -> Result<Self, EvalError> {
+        if Zero::is_zero(self) {
+            return Err(EvalError::DivisionByZero);
+        }
+        Ok(self.recip())
+    }
+
+    fn reciprocal(n: i64, _context: &()) -> Result<Self, EvalError> {
+        if n == 0 {
+            return Err(EvalError::DivisionByZero);
+        }
+        Ok(BigRational::new(BigInt::one(), BigInt::from(n)))
+    }
+
+    /// The exact power, failing if it is irrational.
+    fn powr(&self, exponent: &BigRational) -> Result<Self, EvalError> {
+        rational_power(self, exponent)
+    }
+}
+
+/// Floating-point coefficients, for numerical approximations. Rounding
+/// errors can leave tiny non-zero values where exact arithmetic cancels.
+impl Coefficient for f64 {
+    type Context = ();
+
+    fn from_integer(value: &BigInt, _context: &()) -> Self {
+        value.to_f64().unwrap_or(f64::NAN)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0.0
+    }
+
+    fn is_one(&self) -> bool {
+        *self == 1.0
+    }
+
+    fn is_negative(&self) -> bool {
+        *self < 0.0
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn neg(&self) -> Self {
+        -self
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self * other
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self - other
+    }
+
+    fn to_rational(&self) -> Option<BigRational> {
+        BigRational::from_f64(*self)
+    }
+}
+
+impl Field for f64 {
+    fn from_rational(value: &BigRational, _context: &()) -> Result<Self, EvalError> {
+        Ok(value.to_f64().unwrap_or(f64::NAN))
+    }
+
+    fn inverse(&self) -> Result<Self, EvalError> {
+        if *self == 0.0 {
+            return Err(EvalError::DivisionByZero);
+        }
+        Ok(self.recip())
+    }
+
+    /// The real power, failing for even roots of negative numbers.
+    fn powr(&self, exponent: &BigRational) -> Result<Self, EvalError> {
+        let odd = |n: &BigInt| n % 2u32 != BigInt::zero();
+        if *self >= 0.0 {
+            return Ok(self.powf(exponent.to_f64().unwrap_or(f64::NAN)));
+        }
+        if !odd(exponent.denom()) {
+            return Err(EvalError::NoRealPower(
+                self.to_string(),
+                exponent.to_string(),
+            ));
+        }
+        // An odd root keeps the sign, which an odd power then keeps too.
+        let power = self.abs().powf(exponent.to_f64().unwrap_or(f64::NAN));
+        Ok(if odd(exponent.numer()) { -power } else { power })
+    }
+}
@@ -0,0 +1,471 @@
+//! Arithmetic on dense truncated power series, given as their first `len`
+//! coefficients; only products accept shorter operands. Products use the
+//! coefficient ring's `mul_truncated`, which is a Karatsuba short product
+//! over the integers for rationals, and inverse, logarithm, exponential,
+//! powers and sine/cosine use Newton iteration on top of it, so all run in
+//! `O(M(len))` coefficient operations. Short series use the first-order
+//! recurrences instead, which are faster there.
+//!
+//! Rational coefficients usually grow with the degree, as the `1/n!` of
+//! `exp(x)` do, and so does the cost of each coefficient operation: the
+//! running time grows much faster than `M(len)`. Modulo a prime, degree
+//! 10,000 takes well under a second. With rationals, expansions such as
+//! `exp(x)` or `sin(x)/cos(x)` take about a second at degree 500 and roughly
+//! ten times as long for each doubling of the degree beyond that, so degree
+//! 10,000 is out of reach for them.
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+
+use crate::coefficient;
+use crate::evaluator::EvalError;
+use crate::factor::lcm;
+
+/// Shorter operands are multiplied by the schoolbook method.
+const KARATSUBA_THRESHOLD: usize = 32;
+
+/// Shorter results are computed by first-order recurrences.
+const NEWTON_THRESHOLD: usize = 16;
+
+/// First `len` coefficients of `a b` for rational `a` and `b`.
+pub(crate) fn mul_rational(a: &[BigRational], b: &[BigRational], len: usize) -> Vec<BigRational> {
+    let a = &a[..a.len().min(len)];
+    let b = &b[..b.len().min(len)];
+    let mut result = vec![BigRational::zero(); len];
+    if a.is_empty() || b.is_empty() {
+        return result;
+    }
+
+    // Multiply d_a a and d_b b over the integers, then divide once.
+    let (a, denom_a) = clear_denominators(a);
+    let (b, denom_b) = clear_denominators(b);
+    let denom = denom_a * denom_b;
+    for (r, c) in result.iter_mut().zip(short_product(&a, &b, len)) {
+        *r = BigRational::new(c, denom.clone());
+    }
+    result
+}
+
+/// The integers `d a_k` for the least common denominator `d` of `a`.
+fn clear_denominators(a: &[BigRational]) -> (Vec<BigInt>, BigInt) {
+    let denom = a.iter().fold(BigInt::one(), |d, c| lcm(&d, c.denom()));
+    let numers = a.iter().map(|c| c.numer() * (&denom / c.denom())).collect();
+    (numers, denom)
+}
+
+/// First `len` coefficients of the product of two non-empty integer
+/// polynomials, by Mulders' short product: with `p` about `0.7 len`, the
+/// full product of the first `p` terms of each plus the short products for
+/// `x^p (a_high b_low + a_low b_high)`, whose terms past `len` are never
+/// computed. The product `a_high b_high` starts beyond `len` and is skipped.
+fn short_product(a: &[BigInt], b: &[BigInt], len: usize) -> Vec<BigInt> {
+    let a = &a[..a.len().min(len)];
+    let b = &b[..b.len().min(len)];
+    if a.len().min(b.len()) < KARATSUBA_THRESHOLD {
+        return schoolbook(a, b, len);
+    }
+    if a.len() + b.len() <= len {
+        let mut result = karatsuba(a, b);
+        result.resize(len, BigInt::zero());
+        return result;
+    }
+
+    let p = (7 * len).div_ceil(10);
+    let (a0, a1) = a.split_at(p.min(a.len()));
+    let (b0, b1) = b.split_at(p.min(b.len()));
+    let mut result = vec![BigInt::zero(); len];
+    add_at(&mut result, &karatsuba(a0, b0), 0);
+    if !a1.is_empty() {
+        add_at(&mut result, &short_product(a1, b0, len - p), p);
+    }
+    if !b1.is_empty() {
+        add_at(&mut result, &short_product(a0, b1, len - p), p);
+    }
+    result
+}
+
+/// First `len` coefficients of `a b` by the schoolbook method.
+fn schoolbook(a: &[BigInt], b: &[BigInt], len: usize) -> Vec<BigInt> {
+    let mut result = vec![BigInt::zero(); len];
+    for (i, x) in a.iter().enumerate().take(len) {
+        if x.is_zero() {
+            continue;
+        }
+        for (j, y) in b.iter().enumerate().take(len - i) {
+            result[i + j] += x * y;
+        }
+    }
+    result
+}
+
+/// Full product of two non-empty integer polynomials.
+fn karatsuba(a: &[BigInt], b: &[BigInt]) -> Vec<BigInt> {
+    if a.len().min(b.len()) < KARATSUBA_THRESHOLD {
+        return schoolbook(a, b, a.len() + b.len() - 1);
+    }
+
+    // a = a0 + x^m a1 and b = b0 + x^m b1.
+    let m = a.len().max(b.len()).div_ceil(2);
+    let mut result = vec![BigInt::zero(); a.len() + b.len() - 1];
+    if a.len() <= m || b.len() <= m {
+        // Unbalanced: split only the longer operand.
+        let (short, long) = if a.len() <= m { (a, b) } else { (b, a) };
+        let (low, high) = long.split_at(m);
+        add_at(&mut result, &karatsuba(short, low), 0);
+        add_at(&mut result, &karatsuba(short, high), m);
+        return result;
+    }
+
+    let (a0, a1) = a.split_at(m);
+    let (b0, b1) = b.split_at(m);
+    let z0 = karatsuba(a0, b0);
+    let z2 = karatsuba(a1, b1);
+    let mut z1 = karatsuba(&sum(a0, a1), &sum(b0, b1));
+    for (k, c) in z0.iter().enumerate() {
+        z1[k] -= c;
+    }
+    for (k, c) in z2.iter().enumerate() {
+        z1[k] -= c;
+    }
+    add_at(&mut result, &z0, 0);
+    add_at(&mut result, &z1, m);
+    add_at(&mut result, &z2, 2 * m);
+    result
+}
+
+fn sum(a: &[BigInt], b: &[BigInt]) -> Vec<BigInt> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = long.to_vec();
+    for (r, c) in result.iter_mut().zip(short) {
+        *r += c;
+    }
+    result
+}
+
+/// Adds `x^shift b` to `a`, ignoring terms beyond its length.
+fn add_at(a: &mut [BigInt], b: &[BigInt], shift: usize) {
+    for (r, c) in a.iter_mut().skip(shift).zip(b) {
+        *r += c;
+    }
+}
+
+/// First `len` coefficients of `a b`.
+fn mul<R: coefficient::Coefficient>(a: &[R], b: &[R], len: usize, cx: &R::Context) -> Vec<R> {
+    R::mul_truncated(a, b, len, cx)
+}
+
+/// First `len` coefficients of `1/a`; `a_0` must be non-zero. Newton's
+/// iteration `g <- g + g (1 - a g)` doubles the known terms each step.
+pub(crate) fn inverse<R: coefficient::Field>(
+    a: &[R],
+    len: usize,
+    cx: &R::Context,
+) -> Result<Vec<R>, EvalError> {
+    if len <= NEWTON_THRESHOLD {
+        return inverse_recurrence(a, len, cx);
+    }
+
+    let g = inverse(a, len.div_ceil(2), cx)?;
+    let mut error = neg(&mul(a, &g, len, cx));
+    error[0] = error[0].add(&R::one(cx));
+    Ok(add(&g, &mul(&g, &error, len, cx)))
+}
+
+/// `a_0 g_n = -sum_{k=1..n} a_k g_{n-k}`.
+fn inverse_recurrence<R: coefficient::Field>(
+    a: &[R],
+    len: usize,
+    cx: &R::Context,
+) -> Result<Vec<R>, EvalError> {
+    let mut result = vec![R::zero(cx); len];
+    if len == 0 {
+        return Ok(result);
+    }
+
+    let a0_inverse = a[0].inverse()?;
+    result[0] = a0_inverse.clone();
+    for n in 1..len {
+        let mut sum = R::zero(cx);
+        for k in 1..=n.min(a.len() - 1) {
+            sum = sum.add(&a[k].mul(&result[n - k]));
+        }
+        result[n] = sum.neg().mul(&a0_inverse);
+    }
+    Ok(result)
+}
+
+/// `k a_k`, the coefficients of `x a'`.
+fn derivative<R: coefficient::Coefficient>(a: &[R], cx: &R::Context) -> Vec<R> {
+    a.iter()
+        .enumerate()
+        .map(|(k, c)| c.mul(&R::from_integer(&BigInt::from(k), cx)))
+        .collect()
+}
+
+/// First `len` coefficients of `log(a)`, the integral of `a'/a`; `a_0`
+/// must be one.
+pub(crate) fn log<R: coefficient::Field>(
+    a: &[R],
+    len: usize,
+    cx: &R::Context,
+) -> Result<Vec<R>, EvalError> {
+    if len <= NEWTON_THRESHOLD {
+        return log_recurrence(a, len, cx);
+    }
+
+    // x h' = x a' / a; dividing by k integrates.
+    let a_derivative = derivative(&a[..a.len().min(len)], cx);
+    integrated(mul(&a_derivative, &inverse(a, len, cx)?, len, cx), cx)
+}
+
+/// `h = log(f)` from `f h' = f'`:
+/// `n h_n = n f_n - sum_{k=1..n-1} k h_k f_{n-k}`.
+fn log_recurrence<R: coefficient::Field>(
+    f: &[R],
+    len: usize,
+    cx: &R::Context,
+) -> Result<Vec<R>, EvalError> {
+    let mut dh = vec![R::zero(cx); len];
+    for n in 1..len {
+        let mut sum = f[n].mul(&R::from_integer(&BigInt::from(n), cx));
+        for k in 1..n {
+            sum = sum.sub(&dh[k].mul(&f[n - k]));
+        }
+        dh[n] = sum;
+    }
+    integrated(dh, cx)
+}
+
+/// Coefficients `c_k / k` of the series whose `x h'` is `c`, with zero
+/// constant term.
+fn integrated<R: coefficient::Field>(c: Vec<R>, cx: &R::Context) -> Result<Vec<R>, EvalError> {
+    c.into_iter()
+        .enumerate()
+        .map(|(n, c)| {
+            if n == 0 {
+                Ok(R::zero(cx))
+            } else if c.is_zero() {
+                Ok(c)
+            } else {
+                Ok(c.mul(&R::reciprocal(n as i64, cx)?))
+            }
+        })
+        .collect()
+}
+
+/// First `len` coefficients of `exp(g)`; `g_0` must be zero. Newton's
+/// iteration is `h <- h (1 + g - log(h))`.
+pub(crate) fn exp<R: coefficient::Field>(
+    g: &[R],
+    len: usize,
+    cx: &R::Context,
+) -> Result<Vec<R>, EvalError> {
+    if len <= NEWTON_THRESHOLD {
+        return exp_recurrence(g, len, cx);
+    }
+
+    let mut h = exp(g, len.div_ceil(2), cx)?;
+    h.resize(len, R::zero(cx));
+    let mut factor = sub(&g[..g.len().min(len)], &log(&h, len, cx)?);
+    factor[0] = factor[0].add(&R::one(cx));
+    Ok(mul(&h, &factor, len, cx))
+}
+
+/// `h = exp(g)` from `h' = h g'`: `n h_n = sum_{k=1..n} k g_k h_{n-k}`.
+fn exp_recurrence<R: coefficient::Field>(
+    g: &[R],
+    len: usize,
+    cx: &R::Context,
+) -> Result<Vec<R>, EvalError> {
+    let dg = derivative(&g[..g.len().min(len)], cx);
+    let mut result: Vec<R> = Vec::with_capacity(len);
+    for n in 0..len {
+        if n == 0 {
+            result.push(R::one(cx));
+            continue;
+        }
+        let mut sum = R::zero(cx);
+        for k in 1..=n.min(dg.len() - 1) {
+            sum = sum.add(&dg[k].mul(&result[n - k]));
+        }
+        result.push(sum.mul(&R::reciprocal(n as i64, cx)?));
+    }
+    Ok(result)
+}
+
+/// First `len` coefficients of `(a/a_0)^e`, for `a_0` non-zero.
+pub(crate) fn pow<R: coefficient::Field>(
+    a: &[R],
+    e: &BigRational,
+    len: usize,
+    cx: &R::Context,
+) -> Result<Vec<R>, EvalError> {
+    let e = R::from_rational(e, cx)?;
+    if len <= NEWTON_THRESHOLD {
+        return pow_recurrence(a, &e, len, cx);
+    }
+
+    let a0_inverse = a[0].inverse()?;
+    let unit: Vec<R> = a.iter().take(len).map(|c| c.mul(&a0_inverse)).collect();
+    let log = log(&unit, len, cx)?;
+    exp(&log.iter().map(|c| c.mul(&e)).collect::<Vec<_>>(), len, cx)
+}
+
+/// `p = (a/a_0)^e` from `p' a = e p a'`, which gives J.C.P. Miller's
+/// recurrence `n a_0 p_n = sum_{k=1..n} ((e + 1) k - n) a_k p_{n-k}`.
+fn pow_recurrence<R: coefficient::Field>(
+    a: &[R],
+    e: &R,
+    len: usize,
+    cx: &R::Context,
+) -> Result<Vec<R>, EvalError> {
+    let integer = |n: usize| R::from_integer(&BigInt::from(n), cx);
+    let next = e.add(&R::one(cx));
+    let a0_inverse = a[0].inverse()?;
+    let mut result: Vec<R> = Vec::with_capacity(len);
+    for n in 0..len {
+        if n == 0 {
+            result.push(R::one(cx));
+            continue;
+        }
+        let mut sum = R::zero(cx);
+        for k in 1..=n.min(a.len() - 1) {
+            let weight = next.mul(&integer(k)).sub(&integer(n));
+            sum = sum.add(&weight.mul(&a[k]).mul(&result[n - k]));
+        }
+        let divisor = a0_inverse.mul(&R::reciprocal(n as i64, cx)?);
+        result.push(sum.mul(&divisor));
+    }
+    Ok(result)
+}
+
+/// First `len` coefficients of `sin(g)` and `cos(g)`; `g_0` must be zero.
+/// This is Newton's iteration `E <- E (1 + i g - log(E))` for
+/// `E = exp(i g) = c + i s`, kept real: with `r = c^2 + s^2 - 1` and
+/// `theta` the integral of `(s' c - c' s)(1 - r)`, `log(E) = r/2 + i theta` to
+/// twice the known terms.
+pub(crate) fn sin_cos<R: coefficient::Field>(
+    g: &[R],
+    len: usize,
+    cx: &R::Context,
+) -> Result<(Vec<R>, Vec<R>), EvalError> {
+    if len <= NEWTON_THRESHOLD {
+        return sin_cos_recurrence(g, len, cx);
+    }
+
+    let (mut s, mut c) = sin_cos(g, len.div_ceil(2), cx)?;
+    s.resize(len, R::zero(cx));
+    c.resize(len, R::zero(cx));
+    let mut r = add(&mul(&c, &c, len, cx), &mul(&s, &s, len, cx));
+    r[0] = r[0].sub(&R::one(cx));
+    let w = sub(
+        &mul(&derivative(&s, cx), &c, len, cx),
+        &mul(&derivative(&c, cx), &s, len, cx),
+    );
+    let theta = integrated(sub(&w, &mul(&w, &r, len, cx)), cx)?;
+    let delta = sub(&g[..g.len().min(len)], &theta);
+    let half = R::reciprocal(2, cx)?;
+    let mut scale: Vec<R> = r.iter().map(|c| c.mul(&half).neg()).collect();
+    scale[0] = scale[0].add(&R::one(cx));
+
+    let sin = add(&mul(&s, &scale, len, cx), &mul(&c, &delta, len, cx));
+    let cos = sub(&mul(&c, &scale, len, cx), &mul(&s, &delta, len, cx));
+    Ok((sin, cos))
+}
+
+/// `sin` and `cos` together, from `s' = c g'` and `c' = -s g'`:
+/// `n s_n = sum_{k=1..n} k g_k c_{n-k}` and likewise for `c`.
+fn sin_cos_recurrence<R: coefficient::Field>(
+    g: &[R],
+    len: usize,
+    cx: &R::Context,
+) -> Result<(Vec<R>, Vec<R>), EvalError> {
+    let dg = derivative(&g[..g.len().min(len)], cx);
+    let mut sin = vec![R::zero(cx); len];
+    let mut cos = vec![R::zero(cx); len];
+    if let Some(c0) = cos.first_mut() {
+        *c0 = R::one(cx);
+    }
+    for n in 1..len {
+        let mut s = R::zero(cx);
+        let mut c = R::zero(cx);
+        for k in 1..=n.min(dg.len() - 1) {
+            s = s.add(&dg[k].mul(&cos[n - k]));
+            c = c.sub(&dg[k].mul(&sin[n - k]));
+        }
+        let inverse = R::reciprocal(n as i64, cx)?;
+        sin[n] = s.mul(&inverse);
+        cos[n] = c.mul(&inverse);
+    }
+    Ok((sin, cos))
+}
+
+/// Coefficients of `a + b`, as many as the longer operand has.
+fn add<R: coefficient::Coefficient>(a: &[R], b: &[R]) -> Vec<R> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = long.to_vec();
+    for (r, c) in result.iter_mut().zip(short) {
+        *r = r.add(c);
+    }
+    result
+}
+
+fn neg<R: coefficient::Coefficient>(a: &[R]) -> Vec<R> {
+    a.iter().map(R::neg).collect()
+}
+
+fn sub<R: coefficient::Coefficient>(a: &[R], b: &[R]) -> Vec<R> {
+    add(a, &neg(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rationals(len: usize, f: impl Fn(i64) -> (i64, i64)) -> Vec<BigRational> {
+        (0..len as i64)
+            .map(|k| {
+                let (n, d) = f(k);
+                BigRational::new(n.into(), d.into())
+            })
+            .collect()
+    }
+
+    fn schoolbook(a: &[BigRational], b: &[BigRational], len: usize) -> Vec<BigRational> {
+        let mut result = vec![BigRational::zero(); len];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate().take(len.saturating_sub(i)) {
+                result[i + j] += x * y;
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn karatsuba_matches_schoolbook() {
+        let a = rationals(150, |k| (k * k - 7, k % 5 + 1));
+        let b = rationals(97, |k| (3 - 2 * k, 1 + k % 3));
+        for len in [1, 40, 97, 150, 246, 300] {
+            assert_eq!(mul_rational(&a, &b, len), schoolbook(&a, &b, len));
+        }
+        assert_eq!(mul_rational(&a[..1], &b, 97), schoolbook(&a[..1], &b, 97));
+        assert_eq!(mul_rational(&a, &[], 5), vec![BigRational::zero(); 5]);
+    }
+
+    #[test]
+    fn newton_iteration_matches_recurrences() {
+        let len = 3 * NEWTON_THRESHOLD + 5;
+        let g = rationals(len, |k| (if k == 0 { 0 } else { k % 7 - 3 }, k % 4 + 1));
+        let a = rationals(len, |k| (k % 5 - 2 + k % 2, k % 3 + 1));
+        let mut f = g.clone();
+        f[0] = BigRational::one();
+
+        assert_eq!(inverse(&a, len, &()), inverse_recurrence(&a, len, &()));
+        assert_eq!(log(&f, len, &()), log_recurrence(&f, len, &()));
+        assert_eq!(exp(&g, len, &()), exp_recurrence(&g, len, &()));
+        assert_eq!(sin_cos(&g, len, &()), sin_cos_recurrence(&g, len, &()));
+        let e = BigRational::new((-5).into(), 3.into());
+        assert_eq!(pow(&a, &e, len, &()), pow_recurrence(&a, &e, len, &()));
+    }
+}
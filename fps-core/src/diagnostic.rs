@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fmt::Write;
+
+/// Byte range `start..end` into the original input string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Zero-width span at `offset`, used for positions between tokens.
+    pub fn point(offset: usize) -> Self {
+        Self::new(offset, offset)
+    }
+
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+/// An error that can point at the part of the input that caused it.
+pub trait Diagnostic: Error {
+    fn span(&self) -> Span;
+}
+
+/// Renders `error` as its message followed by the offending input line with
+/// the span underlined:
+///
+/// ```text
+/// Division by series with zero constant term
+///   |
+/// 1 | 1/(x - x)
+///   |   ^^^^^^^
+/// ```
+pub fn render(input: &str, error: &dyn Diagnostic) -> String {
+    let span = error.span();
+    let start = floor_char_boundary(input, span.start);
+    let end = floor_char_boundary(input, span.end.max(start));
+
+    let line_start = input[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = input[start..].find('\n').map_or(input.len(), |i| start + i);
+    let line = &input[line_start..line_end];
+    let line_number = input[..line_start].matches('\n').count() + 1;
+
+    let column = input[line_start..start].chars().count();
+    let width = input[start..end.min(line_end)].chars().count().max(1);
+
+    let gutter = " ".repeat(line_number.to_string().len());
+    let mut rendered = String::new();
+    let _ = writeln!(rendered, "{}", error);
+    let _ = writeln!(rendered, "{} |", gutter);
+    let _ = writeln!(rendered, "{} | {}", line_number, line);
+    let _ = write!(
+        rendered,
+        "{} | {}{}",
+        gutter,
+        " ".repeat(column),
+        "^".repeat(width)
+    );
+    rendered
+}
+
+fn floor_char_boundary(input: &str, offset: usize) -> usize {
+    let mut offset = offset.min(input.len());
+    while !input.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use thiserror::Error;
+
+    #[derive(Debug, Error)]
+    #[error("Something went wrong")]
+    struct TestError(Span);
+
+    impl Diagnostic for TestError {
+        fn span(&self) -> Span {
+            self.0
+        }
+    }
+
+    #[test]
+    fn underlines_span() {
+        let rendered = render("1 + sin(2)", &TestError(Span::new(4, 10)));
+        assert_eq!(
+            rendered,
+            "Something went wrong\n  |\n1 | 1 + sin(2)\n  |     ^^^^^^"
+        );
+    }
+
+    #[test]
+    fn empty_span_at_end_gets_single_caret() {
+        let rendered = render("1 +", &TestError(Span::point(3)));
+        assert_eq!(rendered, "Something went wrong\n  |\n1 | 1 +\n  |    ^");
+    }
+
+    #[test]
+    fn picks_line_containing_span() {
+        let rendered = render("1 +\n2 $ 3", &TestError(Span::new(6, 7)));
+        assert_eq!(rendered, "Something went wrong\n  |\n2 | 2 $ 3\n  |   ^");
+    }
+}
@@ -1,86 +1,1428 @@
-use crate::{parser::Expr, series::Series};
-use num_traits::ToPrimitive;
+use crate::coefficient;
+use crate::diagnostic::{Diagnostic, Span};
+use crate::functions::FunctionRegistry;
+use crate::modint::{self, ModInt};
+use crate::multimodular;
+use crate::multivariate::{MultiSeries, Truncation};
+use crate::parser::{Expr, ExprKind};
+use crate::polynomial::Polynomial;
+use crate::rational_function::RationalFunction;
+use crate::series::{DEFAULT_VARIABLE, ExpansionPoint, Series, rational_power};
+use num_bigint::BigInt;
+use num_rational::{BigRational, Rational64};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::rc::Rc;
 use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum EvalError {
     #[error("Division by series with zero constant term")]
     DivisionByZero,
-    #[error("Exponent must be a constant integer")]
-    NonIntegerExponent,
+    #[error("Non-constant exponent requires base with constant term equal to 1")]
+    SeriesExponentRequiresUnitBase,
     #[error("Exponent magnitude is too large")]
     ExponentTooLarge,
     #[error("{0} requires series with zero constant term")]
     FunctionRequiresZeroConstant(&'static str),
+    #[error("Non-integer power requires series with non-zero constant term")]
+    NonIntegerPowerOfZero,
+    #[error("Constant term {0} raised to {1} is not rational")]
+    IrrationalPower(String, String),
     #[error("log requires series with constant term equal to 1")]
     LogRequiresUnitConstant,
+    #[error("{0} requires series without negative powers")]
+    NegativePowers(&'static str),
+    #[error("{0} requires series with integer exponents")]
+    FractionalExponents(&'static str),
+    #[error("int of a series with an x^-1 term is a logarithm")]
+    LogarithmicIntegral,
+    #[error("Integration constant must be a constant")]
+    NonConstantIntegrationConstant,
+    #[error("revert requires series with zero constant term and non-zero linear term")]
+    NotReversible,
+    #[error("{0} is only supported for univariate series")]
+    UnivariateOnly(&'static str),
+    #[error("{0} does not give a polynomial")]
+    NotPolynomial(&'static str),
+    #[error("{0} does not give a rational function")]
+    NotRational(&'static str),
+    #[error("{0} has no inverse modulo {1}")]
+    NotInvertible(String, u64),
+    #[error("Constant term {0} raised to {1} has no value modulo {2}")]
+    NoModularPower(String, String, u64),
+    #[error("Constant term {0} raised to {1} is not real")]
+    NoRealPower(String, String),
+    #[error("Coefficient {0} has no rational value")]
+    NoRationalValue(String),
+    #[error("Modulus {0} must be a prime below 2^32")]
+    InvalidModulus(u64),
+    #[error("Coefficient index must be a constant")]
+    InvalidCoefficientIndex,
+    #[error("Coefficient needs {0} more term(s) of precision")]
+    InsufficientPrecision(usize),
+    #[error("Factorial argument must be a constant")]
+    NonConstantFactorial,
+    #[error("Factorial argument must be a non-negative integer")]
+    NonIntegerFactorial,
+    #[error("Factorial argument is too large")]
+    FactorialTooLarge,
+    #[error("Expression mixes the variables {0} and {1}")]
+    MultipleVariables(char, char),
+    #[error("Unknown variable {0}, expected {1}")]
+    UnexpectedVariable(char, char),
+    #[error("Unknown variable {0}")]
+    UnknownVariable(char),
+    #[error("Expected {0} truncation bounds, got {1}")]
+    TruncationArity(usize, usize),
+    #[error("Undefined name: {0}")]
+    UndefinedName(String),
+    #[error("{0} is a function and must be called with arguments")]
+    FunctionUsedAsValue(String),
+    #[error("{0} is not a function")]
+    NotAFunction(String),
+    #[error("{0} expects {1} argument(s), got {2}")]
+    ArityMismatch(String, usize, usize),
+    /// Error reported by a function registered outside this crate.
+    #[error("{0}")]
+    Custom(String),
 }
 
-pub fn evaluate(expr: &Expr, max_degree: usize) -> Result<Series, EvalError> {
-    match expr {
-        Expr::Num(value) => Ok(Series::constant(value.clone(), max_degree)),
-        Expr::Variable(_) => Ok(Series::variable(max_degree)),
-        Expr::Add(lhs, rhs) => {
-            let left = evaluate(lhs, max_degree)?;
-            let right = evaluate(rhs, max_degree)?;
-            Ok(left.add(&right))
+/// An [`EvalError`] together with the span of the expression that raised it.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("{error}")]
+pub struct SpannedEvalError {
+    pub error: EvalError,
+    pub span: Span,
+}
+
+impl EvalError {
+    pub fn at(self, span: Span) -> SpannedEvalError {
+        SpannedEvalError { error: self, span }
+    }
+}
+
+impl Diagnostic for SpannedEvalError {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// Evaluates `expr` as a series in its only variable, or in
+/// [`DEFAULT_VARIABLE`] if the expression is constant, using the standard
+/// functions.
+pub fn evaluate(expr: &Expr, max_degree: usize) -> Result<Series, SpannedEvalError> {
+    let variable = detect_variable(expr)?.unwrap_or(DEFAULT_VARIABLE);
+    evaluate_in(expr, variable, max_degree, &FunctionRegistry::standard())
+}
+
+/// Evaluates `expr` as a series around `point` in its only variable, using
+/// the standard functions. See [`evaluate_around`].
+pub fn evaluate_at(
+    expr: &Expr,
+    point: &ExpansionPoint,
+    max_degree: usize,
+) -> Result<Series, SpannedEvalError> {
+    let variable = detect_variable(expr)?.unwrap_or(DEFAULT_VARIABLE);
+    evaluate_around(
+        expr,
+        variable,
+        point,
+        max_degree,
+        &FunctionRegistry::standard(),
+    )
+}
+
+/// Evaluates `expr` as a series in `variable`; any other variable is an error.
+///
+/// Division by series with leading zeros, derivatives and similar operations
+/// lose terms, so the expansion is repeated at a higher working degree until
+/// the result is known up to `max_degree` and every coefficient extracted
+/// with `[x^n]` is exact. The result is then truncated to `O(x^(max_degree+1))`.
+pub fn evaluate_in(
+    expr: &Expr,
+    variable: char,
+    max_degree: usize,
+    functions: &FunctionRegistry<Series>,
+) -> Result<Series, SpannedEvalError> {
+    let point = ExpansionPoint::default();
+    evaluate_around(expr, variable, &point, max_degree, functions)
+}
+
+/// Like [`evaluate_in`], but expands around `point` by substituting
+/// `x = a + t`, or `x = 1/t` at infinity. The result is a series in `t`,
+/// meant to be shown with [`Series::display_at`]; `[x^n]` extracts the
+/// coefficient of `(x-a)^n`, or of `x^n = t^-n` at infinity.
+pub fn evaluate_around(
+    expr: &Expr,
+    variable: char,
+    point: &ExpansionPoint,
+    max_degree: usize,
+    functions: &FunctionRegistry<Series>,
+) -> Result<Series, SpannedEvalError> {
+    if calls_any(expr, functions) {
+        return expand(expr, variable, point, &(), max_degree, MAX_EXTRA_DEGREE, functions);
+    }
+    // Reducing rational functions costs far more than a truncated series,
+    // so they are only used for coefficients too far out for the series.
+    match expand(expr, variable, point, &(), max_degree, RATIONAL_EXTRA_DEGREE, functions) {
+        Err(SpannedEvalError {
+            error: EvalError::ExponentTooLarge,
+            ..
+        }) => {
+            if let Some(result) = expand_rational(expr, variable, point, max_degree, functions) {
+                return result;
+            }
+            expand(expr, variable, point, &(), max_degree, MAX_EXTRA_DEGREE, functions)
         }
-        Expr::Sub(lhs, rhs) => {
-            let left = evaluate(lhs, max_degree)?;
-            let right = evaluate(rhs, max_degree)?;
-            Ok(left.sub(&right))
+        result => result,
+    }
+}
+
+/// The expansion of `expr` as a rational function, or `None` if it is not
+/// one. Expressions built from +, -, *, / and small integer powers alone
+/// are rational functions, whose coefficients follow from a linear
+/// recurrence however far out they are. Calls of `functions` are left to
+/// the series path, as are powers beyond `SMALL_RATIONAL_DEGREE`, whose
+/// exact expansion costs more than a truncated series.
+fn expand_rational(
+    expr: &Expr,
+    variable: char,
+    point: &ExpansionPoint,
+    max_degree: usize,
+    functions: &FunctionRegistry<Series>,
+) -> Option<Result<Series, SpannedEvalError>> {
+    if calls_any(expr, functions) {
+        return None;
+    }
+    let rational = Evaluator {
+        domain: Rational {
+            variable,
+            point,
+            max_degree: SMALL_RATIONAL_DEGREE,
+        },
+        functions: &FunctionRegistry::new(),
+    }
+    .evaluate(expr);
+    match rational {
+        Ok(rational) => Some(Ok(rational.to_series(max_degree))),
+        Err(SpannedEvalError {
+            error: EvalError::NotRational(_) | EvalError::ExponentTooLarge,
+            ..
+        }) => None,
+        Err(error) => Some(Err(error)),
+    }
+}
+
+/// Like [`evaluate_in`], but with coefficients in the field `R` given by
+/// `context`, such as `f64` or residues modulo a prime. Literals are
+/// converted with [`coefficient::Field::from_rational`]; exponents,
+/// factorials and coefficient indices built from literals keep their exact
+/// values.
+pub fn evaluate_over<R: coefficient::Field>(
+    expr: &Expr,
+    variable: char,
+    context: &R::Context,
+    max_degree: usize,
+    functions: &FunctionRegistry<Series<R>>,
+) -> Result<Series<R>, SpannedEvalError> {
+    let point = ExpansionPoint::default();
+    expand(expr, variable, &point, context, max_degree, MAX_EXTRA_DEGREE, functions)
+}
+
+/// Like [`evaluate_in`], but with coefficients modulo the prime `modulus`.
+/// Fails with [`EvalError::NotInvertible`] where a computation divides by a
+/// multiple of the prime, such as `exp(x)` past degree `modulus - 1`.
+pub fn evaluate_modular(
+    expr: &Expr,
+    variable: char,
+    modulus: u64,
+    max_degree: usize,
+    functions: &FunctionRegistry<Series<ModInt>>,
+) -> Result<Series<ModInt>, SpannedEvalError> {
+    modint::check_modulus(modulus).map_err(|e| e.at(expr.span))?;
+    evaluate_over(expr, variable, &modulus, max_degree, functions)
+}
+
+/// Like [`evaluate_around`] with the standard functions, but computes modulo
+/// word-size primes and recovers the rational coefficients by the Chinese
+/// remainder theorem and rational reconstruction, which avoids the growth of
+/// fractions in intermediate results. Primes are added until the recovered
+/// coefficients are confirmed modulo one more prime; primes modulo which the
+/// expression fails or loses terms, by dividing a denominator or a leading
+/// coefficient, are skipped.
+///
+/// The result is always that of [`evaluate_around`], which is used instead
+/// if the expression fails modulo the first prime, as `log(2+x)` does
+/// everywhere, or if the coefficients need more primes than exact
+/// arithmetic is worth.
+pub fn evaluate_multimodular(
+    expr: &Expr,
+    variable: char,
+    point: &ExpansionPoint,
+    max_degree: usize,
+) -> Result<Series, SpannedEvalError> {
+    let functions = FunctionRegistry::standard();
+    let exact = || evaluate_around(expr, variable, point, max_degree, &functions);
+    let modular_functions = FunctionRegistry::standard();
+    let modular = |p: u64| {
+        expand::<ModInt>(expr, variable, point, &p, max_degree, MAX_EXTRA_DEGREE, &modular_functions)
+            .ok()
+    };
+    let same_shape = |a: &Series<ModInt>, b: &Series<ModInt>| {
+        a.valuation() == b.valuation()
+            && a.ramification() == b.ramification()
+            && a.coeffs().len() == b.coeffs().len()
+    };
+
+    let mut primes = multimodular::primes(2 * (max_degree + 1));
+    let p = primes.next().expect("there are primes below 2^32");
+    let Some(first) = modular(p) else {
+        return exact();
+    };
+    let mut combined = multimodular::Combined::new(first.coeffs(), p);
+    let mut candidate = None;
+    let mut next_attempt = 0;
+    let max_bits = multimodular::max_bits(max_degree);
+    // Primes are 32 bits long, so this leaves room for skipping as many as
+    // are used.
+    for p in primes.take((max_bits / 16) as usize) {
+        if combined.bits() > max_bits {
+            break;
         }
-        Expr::Mul(lhs, rhs) => {
-            let left = evaluate(lhs, max_degree)?;
-            let right = evaluate(rhs, max_degree)?;
-            Ok(left.mul(&right))
+        // Reconstruction is costly, so it is retried only as the product of
+        // the primes grows by an eighth of its size.
+        if candidate.is_none() && combined.bits() >= next_attempt {
+            candidate = combined.reconstruct();
+            next_attempt = combined.bits() + combined.bits() / 8 + 1;
         }
-        Expr::Div(lhs, rhs) => {
-            let left = evaluate(lhs, max_degree)?;
-            let right = evaluate(rhs, max_degree)?;
-            left.div(&right)
+
+        // A prime fails, or gives fewer terms, where it divides a
+        // denominator or a leading coefficient; its residues are skipped.
+        let Some(series) = modular(p).filter(|series| same_shape(series, &first)) else {
+            continue;
+        };
+        if let Some(values) = candidate.take()
+            && multimodular::reduce_to(&values, series.coeffs(), p)
+        {
+            return Ok(first.with_ring(values, ()).trimmed());
         }
-        Expr::Pow(base, exponent) => {
-            let base_series = evaluate(base, max_degree)?;
-            let exponent_series = evaluate(exponent, max_degree)?;
+        combined.add(series.coeffs(), p);
+    }
+    exact()
+}
+
+/// Evaluates `expr` at increasing working degrees until the result is known
+/// up to `max_degree` and every coefficient extracted with `[x^n]` is exact,
+/// then truncates it to `O(x^(max_degree+1))`. Fails with
+/// [`EvalError::ExponentTooLarge`] if that needs more than `max_extra_degree`
+/// terms beyond `max_degree`, as `1/x^n` does for huge `n`.
+fn expand<R: coefficient::Field>(
+    expr: &Expr,
+    variable: char,
+    point: &ExpansionPoint,
+    context: &R::Context,
+    max_degree: usize,
+    max_extra_degree: usize,
+    functions: &FunctionRegistry<Series<R>>,
+) -> Result<Series<R>, SpannedEvalError> {
+    let eval = |working_degree| {
+        Evaluator {
+            domain: Univariate {
+                variable,
+                point,
+                context: context.clone(),
+                max_degree: working_degree,
+            },
+            functions,
+        }
+        .evaluate(expr)
+    };
+
+    let target = Rational64::from_integer(max_degree as i64 + 1);
+    let max_working_degree = max_degree.saturating_add(max_extra_degree);
+    let mut working_degree = max_degree;
+    let mut previous_order = None;
+    loop {
+        if working_degree > max_working_degree {
+            return Err(EvalError::ExponentTooLarge.at(expr.span));
+        }
+        match eval(working_degree) {
+            Err(SpannedEvalError {
+                error: EvalError::InsufficientPrecision(missing),
+                ..
+            }) => working_degree = working_degree.saturating_add(missing),
+            Err(error) => return Err(error),
+            Ok(series) => {
+                let order = series.order();
+                // Stop once precise enough, or if more terms do not help.
+                if order >= target || previous_order.is_some_and(|previous| order <= previous) {
+                    return Ok(series.truncated(max_degree as i64 + 1));
+                }
+                previous_order = Some(order);
+                let missing = (target - order).ceil().to_integer() as usize;
+                working_degree = working_degree.saturating_add(missing);
+            }
+        }
+    }
+}
+
+/// Number of terms beyond the requested degree up to which [`expand`]
+/// raises the working degree.
+const MAX_EXTRA_DEGREE: usize = 100_000;
+
+/// Number of terms beyond the requested degree up to which
+/// [`evaluate_around`] expands a rational function as a series, before it
+/// extracts coefficients from the reduced function instead.
+const RATIONAL_EXTRA_DEGREE: usize = 1000;
+
+/// Degree of the powers up to which [`evaluate_around`] computes with
+/// rational functions.
+const SMALL_RATIONAL_DEGREE: usize = 64;
 
-            if !exponent_series.is_constant() {
-                return Err(EvalError::NonIntegerExponent);
+/// Whether `expr` calls a function in `functions`, rather than one defined
+/// in the expression.
+fn calls_any<V>(expr: &Expr, functions: &FunctionRegistry<V>) -> bool {
+    let mut defined = Vec::new();
+    let mut stack = vec![expr];
+    while let Some(expr) = stack.pop() {
+        match &expr.kind {
+            ExprKind::Num(_) | ExprKind::Variable(_) | ExprKind::Name(_) => {}
+            ExprKind::Call(name, args) => {
+                if functions.contains(name) && !defined.contains(&name) {
+                    return true;
+                }
+                stack.extend(args);
+            }
+            ExprKind::Add(lhs, rhs)
+            | ExprKind::Sub(lhs, rhs)
+            | ExprKind::Mul(lhs, rhs)
+            | ExprKind::Div(lhs, rhs)
+            | ExprKind::Pow(lhs, rhs) => {
+                stack.push(lhs);
+                stack.push(rhs);
+            }
+            ExprKind::Neg(inner) | ExprKind::Factorial(inner) => stack.push(inner),
+            ExprKind::Coefficient { degree, body, .. } => {
+                stack.push(degree);
+                stack.push(body);
+            }
+            ExprKind::Let { value, body, .. } => {
+                stack.push(value);
+                stack.push(body);
             }
+            ExprKind::LetFunction {
+                name, value, body, ..
+            } => {
+                defined.push(name);
+                stack.push(value);
+                stack.push(body);
+            }
+        }
+    }
+    false
+}
+
+/// Evaluates `expr` exactly as a polynomial in `variable`. Anything that does
+/// not give a polynomial, such as `sin` or division with a remainder, fails
+/// with [`EvalError::NotPolynomial`] or another error; callers then fall
+/// back to a truncated series.
+pub fn evaluate_polynomial(
+    expr: &Expr,
+    variable: char,
+    functions: &FunctionRegistry<Polynomial>,
+) -> Result<Polynomial, SpannedEvalError> {
+    Evaluator {
+        domain: Exact {
+            variable,
+            max_degree: usize::MAX,
+        },
+        functions,
+    }
+    .evaluate(expr)
+}
+
+/// Degree up to which [`evaluate_small_polynomial`] expands powers, even if
+/// the requested degree is lower.
+const SMALL_POLYNOMIAL_DEGREE: usize = 64;
+
+/// Like [`evaluate_polynomial`], but fails with [`EvalError::ExponentTooLarge`]
+/// instead of expanding a power beyond degree `max_degree`, or
+/// [`SMALL_POLYNOMIAL_DEGREE`] if that is larger. Front ends use it to print
+/// polynomials in full without computing, say, `(1-x)^3000` exactly only to
+/// find that `1/(1-x)^3000` needs a truncated series anyway.
+pub fn evaluate_small_polynomial(
+    expr: &Expr,
+    variable: char,
+    max_degree: usize,
+    functions: &FunctionRegistry<Polynomial>,
+) -> Result<Polynomial, SpannedEvalError> {
+    Evaluator {
+        domain: Exact {
+            variable,
+            max_degree: max_degree.max(SMALL_POLYNOMIAL_DEGREE),
+        },
+        functions,
+    }
+    .evaluate(expr)
+}
+
+/// Evaluates `expr` exactly as a rational function of `variable`, e.g. for
+/// printing its closed form. Functions other than `D` and `compose` fail
+/// with [`EvalError::NotRational`].
+pub fn evaluate_rational(
+    expr: &Expr,
+    variable: char,
+    functions: &FunctionRegistry<RationalFunction>,
+) -> Result<RationalFunction, SpannedEvalError> {
+    let point = ExpansionPoint::default();
+    Evaluator {
+        domain: Rational {
+            variable,
+            point: &point,
+            max_degree: usize::MAX,
+        },
+        functions,
+    }
+    .evaluate(expr)
+}
+
+/// Whether `expr` (after any definitions) is a coefficient extraction, whose
+/// value is best shown as a plain rational rather than a series.
+pub fn is_coefficient_query(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Coefficient { .. } => true,
+        ExprKind::Let { body, .. } | ExprKind::LetFunction { body, .. } => {
+            is_coefficient_query(body)
+        }
+        _ => false,
+    }
+}
+
+/// Whether `expr` (after any definitions) is a call to `factor`, whose
+/// polynomial value is best shown in factored form.
+pub fn is_factor_query(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Call(name, args) => name == "factor" && args.len() == 1,
+        ExprKind::Let { body, .. } | ExprKind::LetFunction { body, .. } => is_factor_query(body),
+        _ => false,
+    }
+}
+
+/// Evaluates `expr` as a multivariate series in `variables`.
+///
+/// [`Truncation::PerVariable`] bounds are matched to `variables` by position.
+pub fn evaluate_multivariate(
+    expr: &Expr,
+    variables: &[char],
+    truncation: &Truncation,
+    functions: &FunctionRegistry<MultiSeries>,
+) -> Result<MultiSeries, SpannedEvalError> {
+    if let Truncation::PerVariable(bounds) = truncation
+        && bounds.len() != variables.len()
+    {
+        return Err(EvalError::TruncationArity(variables.len(), bounds.len()).at(expr.span));
+    }
+
+    Evaluator {
+        domain: Multivariate {
+            variables: variables.to_vec(),
+            truncation: truncation.clone(),
+        },
+        functions,
+    }
+    .evaluate(expr)
+}
+
+/// Returns the single variable used in `expr`, if any.
+pub fn detect_variable(expr: &Expr) -> Result<Option<char>, SpannedEvalError> {
+    let mut found = None;
+    for (variable, span) in variable_occurrences(expr) {
+        match found {
+            None => found = Some(variable),
+            Some(first) if first != variable => {
+                return Err(EvalError::MultipleVariables(first, variable).at(span));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(found)
+}
+
+/// Returns the distinct variables used in `expr`, sorted by name.
+pub fn collect_variables(expr: &Expr) -> Vec<char> {
+    let mut variables: Vec<char> = variable_occurrences(expr).map(|(c, _)| c).collect();
+    variables.sort_unstable();
+    variables.dedup();
+    variables
+}
+
+/// Every variable occurrence in `expr`, in source order.
+fn variable_occurrences(expr: &Expr) -> impl Iterator<Item = (char, Span)> + '_ {
+    // Children are pushed right to left so variables are visited in source order.
+    let mut stack = vec![expr];
+
+    std::iter::from_fn(move || {
+        while let Some(expr) = stack.pop() {
+            match &expr.kind {
+                ExprKind::Num(_) | ExprKind::Name(_) => {}
+                ExprKind::Variable(c) => return Some((*c, expr.span)),
+                ExprKind::Add(lhs, rhs)
+                | ExprKind::Sub(lhs, rhs)
+                | ExprKind::Mul(lhs, rhs)
+                | ExprKind::Div(lhs, rhs)
+                | ExprKind::Pow(lhs, rhs) => {
+                    stack.push(rhs);
+                    stack.push(lhs);
+                }
+                ExprKind::Neg(inner) | ExprKind::Factorial(inner) => stack.push(inner),
+                ExprKind::Call(_, args) => stack.extend(args.iter().rev()),
+                ExprKind::Coefficient {
+                    variable,
+                    degree,
+                    body,
+                } => {
+                    stack.push(body);
+                    stack.push(degree);
+                    if let Some(c) = variable {
+                        return Some((*c, expr.span));
+                    }
+                }
+                ExprKind::Let { value, body, .. } | ExprKind::LetFunction { value, body, .. } => {
+                    stack.push(body);
+                    stack.push(value);
+                }
+            }
+        }
+        None
+    })
+}
+
+/// Arithmetic the evaluator needs from the series type it computes with.
+///
+/// Registered functions can be written generically over this trait to work
+/// for both univariate and multivariate evaluation.
+pub trait SeriesValue: Clone {
+    fn add(&self, other: &Self) -> Self;
+    fn sub(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+    fn div(&self, other: &Self) -> Result<Self, EvalError>;
+    fn neg(&self) -> Self;
+    fn powi(&self, exponent: i64) -> Result<Self, EvalError>;
+    /// Power with a non-integer rational exponent.
+    fn powr(&self, exponent: &BigRational) -> Result<Self, EvalError>;
+    fn sin(&self) -> Result<Self, EvalError>;
+    fn cos(&self) -> Result<Self, EvalError>;
+    fn exp(&self) -> Result<Self, EvalError>;
+    fn log(&self) -> Result<Self, EvalError>;
+    /// Substitutes `inner` for the series variable.
+    fn compose(&self, _inner: &Self) -> Result<Self, EvalError> {
+        Err(EvalError::UnivariateOnly("compose"))
+    }
+    /// Compositional inverse.
+    fn revert(&self) -> Result<Self, EvalError> {
+        Err(EvalError::UnivariateOnly("revert"))
+    }
+    fn derivative(&self) -> Result<Self, EvalError> {
+        Err(EvalError::UnivariateOnly("D"))
+    }
+    /// Antiderivative with zero constant term.
+    fn integral(&self) -> Result<Self, EvalError> {
+        Err(EvalError::UnivariateOnly("int"))
+    }
+    /// Coefficient of `x^exponent` in a univariate series.
+    fn coefficient(&self, _exponent: &BigRational) -> Result<BigRational, EvalError> {
+        Err(EvalError::UnivariateOnly("coefficient extraction"))
+    }
+    /// The value of a constant series, or `None` if it depends on a variable.
+    /// Fails for a constant with no rational value, such as a residue modulo
+    /// a prime.
+    fn as_constant(&self) -> Result<Option<BigRational>, EvalError>;
+}
+
+impl<R: coefficient::Field> SeriesValue for Series<R> {
+    fn add(&self, other: &Self) -> Self {
+        Series::add(self, other)
+    }
+    fn sub(&self, other: &Self) -> Self {
+        Series::sub(self, other)
+    }
+    fn mul(&self, other: &Self) -> Self {
+        Series::mul(self, other)
+    }
+    fn div(&self, other: &Self) -> Result<Self, EvalError> {
+        Series::div(self, other)
+    }
+    fn neg(&self) -> Self {
+        Series::neg(self)
+    }
+    fn powi(&self, exponent: i64) -> Result<Self, EvalError> {
+        Series::powi(self, exponent)
+    }
+    fn powr(&self, exponent: &BigRational) -> Result<Self, EvalError> {
+        Series::powr(self, exponent)
+    }
+    fn sin(&self) -> Result<Self, EvalError> {
+        Series::sin(self)
+    }
+    fn cos(&self) -> Result<Self, EvalError> {
+        Series::cos(self)
+    }
+    fn exp(&self) -> Result<Self, EvalError> {
+        Series::exp(self)
+    }
+    fn log(&self) -> Result<Self, EvalError> {
+        Series::log(self)
+    }
+    fn compose(&self, inner: &Self) -> Result<Self, EvalError> {
+        Series::compose(self, inner)
+    }
+    fn revert(&self) -> Result<Self, EvalError> {
+        self.reversion()
+    }
+    fn derivative(&self) -> Result<Self, EvalError> {
+        Series::derivative(self)
+    }
+    fn integral(&self) -> Result<Self, EvalError> {
+        Series::integral(self, &R::zero(self.context()))
+    }
+    fn as_constant(&self) -> Result<Option<BigRational>, EvalError> {
+        if !self.is_constant() {
+            return Ok(None);
+        }
+        let constant = self.constant_term();
+        constant
+            .to_rational()
+            .map(Some)
+            .ok_or_else(|| EvalError::NoRationalValue(constant.to_string()))
+    }
+}
 
-            let exponent_value = exponent_series.constant_term();
-            if !exponent_value.is_integer() {
-                return Err(EvalError::NonIntegerExponent);
+impl SeriesValue for MultiSeries {
+    fn add(&self, other: &Self) -> Self {
+        MultiSeries::add(self, other)
+    }
+    fn sub(&self, other: &Self) -> Self {
+        MultiSeries::sub(self, other)
+    }
+    fn mul(&self, other: &Self) -> Self {
+        MultiSeries::mul(self, other)
+    }
+    fn div(&self, other: &Self) -> Result<Self, EvalError> {
+        MultiSeries::div(self, other)
+    }
+    fn neg(&self) -> Self {
+        MultiSeries::neg(self)
+    }
+    fn powi(&self, exponent: i64) -> Result<Self, EvalError> {
+        MultiSeries::powi(self, exponent)
+    }
+    fn powr(&self, exponent: &BigRational) -> Result<Self, EvalError> {
+        MultiSeries::powr(self, exponent)
+    }
+    fn sin(&self) -> Result<Self, EvalError> {
+        MultiSeries::sin(self)
+    }
+    fn cos(&self) -> Result<Self, EvalError> {
+        MultiSeries::cos(self)
+    }
+    fn exp(&self) -> Result<Self, EvalError> {
+        MultiSeries::exp(self)
+    }
+    fn log(&self) -> Result<Self, EvalError> {
+        MultiSeries::log(self)
+    }
+    fn as_constant(&self) -> Result<Option<BigRational>, EvalError> {
+        Ok(self.is_constant().then(|| self.constant_term()))
+    }
+}
+
+impl SeriesValue for Polynomial {
+    fn add(&self, other: &Self) -> Self {
+        Polynomial::add(self, other)
+    }
+    fn sub(&self, other: &Self) -> Self {
+        Polynomial::sub(self, other)
+    }
+    fn mul(&self, other: &Self) -> Self {
+        Polynomial::mul(self, other)
+    }
+    fn div(&self, other: &Self) -> Result<Self, EvalError> {
+        Polynomial::div(self, other)
+    }
+    fn neg(&self) -> Self {
+        Polynomial::neg(self)
+    }
+    fn powi(&self, exponent: i64) -> Result<Self, EvalError> {
+        Polynomial::powi(self, exponent)
+    }
+    fn powr(&self, _exponent: &BigRational) -> Result<Self, EvalError> {
+        Err(EvalError::NotPolynomial("non-integer power"))
+    }
+    fn sin(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotPolynomial("sin"))
+    }
+    fn cos(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotPolynomial("cos"))
+    }
+    fn exp(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotPolynomial("exp"))
+    }
+    fn log(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotPolynomial("log"))
+    }
+    fn compose(&self, inner: &Self) -> Result<Self, EvalError> {
+        Ok(Polynomial::compose(self, inner))
+    }
+    fn revert(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotPolynomial("revert"))
+    }
+    fn derivative(&self) -> Result<Self, EvalError> {
+        Ok(Polynomial::derivative(self))
+    }
+    fn integral(&self) -> Result<Self, EvalError> {
+        Ok(Polynomial::integral(self))
+    }
+    fn coefficient(&self, exponent: &BigRational) -> Result<BigRational, EvalError> {
+        if !exponent.is_integer() || exponent.is_negative() {
+            return Ok(BigRational::zero());
+        }
+        let n = exponent.to_integer().to_usize().unwrap_or(usize::MAX);
+        Ok(Polynomial::coefficient(self, n))
+    }
+    fn as_constant(&self) -> Result<Option<BigRational>, EvalError> {
+        Ok(self.is_constant().then(|| self.constant_term()))
+    }
+}
+
+impl SeriesValue for RationalFunction {
+    fn add(&self, other: &Self) -> Self {
+        RationalFunction::add(self, other)
+    }
+    fn sub(&self, other: &Self) -> Self {
+        RationalFunction::sub(self, other)
+    }
+    fn mul(&self, other: &Self) -> Self {
+        RationalFunction::mul(self, other)
+    }
+    fn div(&self, other: &Self) -> Result<Self, EvalError> {
+        RationalFunction::div(self, other)
+    }
+    fn neg(&self) -> Self {
+        RationalFunction::neg(self)
+    }
+    fn powi(&self, exponent: i64) -> Result<Self, EvalError> {
+        RationalFunction::powi(self, exponent)
+    }
+    fn powr(&self, _exponent: &BigRational) -> Result<Self, EvalError> {
+        Err(EvalError::NotRational("non-integer power"))
+    }
+    fn sin(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotRational("sin"))
+    }
+    fn cos(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotRational("cos"))
+    }
+    fn exp(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotRational("exp"))
+    }
+    fn log(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotRational("log"))
+    }
+    fn compose(&self, inner: &Self) -> Result<Self, EvalError> {
+        RationalFunction::compose(self, inner)
+    }
+    fn revert(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotRational("revert"))
+    }
+    fn derivative(&self) -> Result<Self, EvalError> {
+        Ok(RationalFunction::derivative(self))
+    }
+    fn integral(&self) -> Result<Self, EvalError> {
+        Err(EvalError::NotRational("int"))
+    }
+    fn coefficient(&self, exponent: &BigRational) -> Result<BigRational, EvalError> {
+        if !exponent.is_integer() {
+            return Ok(BigRational::zero());
+        }
+        Ok(RationalFunction::coefficient(self, &exponent.to_integer()))
+    }
+    fn as_constant(&self) -> Result<Option<BigRational>, EvalError> {
+        Ok(self.is_constant().then(|| self.constant_term()))
+    }
+}
+
+/// Builds the leaves of an expression for one kind of series.
+trait Domain {
+    type Value: SeriesValue;
+
+    fn constant(&self, value: BigRational) -> Result<Self::Value, EvalError>;
+    fn variable(&self, name: char) -> Result<Self::Value, EvalError>;
+
+    /// Exponent of the series variable holding the coefficient of
+    /// `x^exponent` requested by `[x^n]`.
+    fn coefficient_exponent(&self, exponent: BigRational) -> BigRational {
+        exponent
+    }
+
+    /// Coefficient of `x^exponent` in `value`, as a constant.
+    fn coefficient(
+        &self,
+        value: &Self::Value,
+        exponent: &BigRational,
+    ) -> Result<Self::Value, EvalError> {
+        self.constant(value.coefficient(exponent)?)
+    }
+
+    /// Fails if `base` raised to `exponent` is too large for this domain to
+    /// compute with.
+    fn check_power(&self, _base: &Self::Value, _exponent: i64) -> Result<(), EvalError> {
+        Ok(())
+    }
+
+    /// Fails if adding or multiplying `lhs` and `rhs` is too large for this
+    /// domain to compute with.
+    fn check_operands(&self, _lhs: &Self::Value, _rhs: &Self::Value) -> Result<(), EvalError> {
+        Ok(())
+    }
+}
+
+/// Fails with [`EvalError::ExponentTooLarge`] if a power of degree
+/// `degree * |exponent|` exceeds `max_degree`.
+fn check_power_degree(degree: usize, exponent: i64, max_degree: usize) -> Result<(), EvalError> {
+    let power_degree = (degree as u64).checked_mul(exponent.unsigned_abs());
+    if power_degree.is_none_or(|d| d > max_degree as u64) {
+        return Err(EvalError::ExponentTooLarge);
+    }
+    Ok(())
+}
+
+struct Univariate<'p, R: coefficient::Coefficient> {
+    variable: char,
+    point: &'p ExpansionPoint,
+    context: R::Context,
+    max_degree: usize,
+}
+
+impl<R: coefficient::Field> Domain for Univariate<'_, R> {
+    type Value = Series<R>;
+
+    fn constant(&self, value: BigRational) -> Result<Series<R>, EvalError> {
+        let value = R::from_rational(&value, &self.context)?;
+        Ok(Series::constant_in(value, self.max_degree, &self.context).with_variable(self.variable))
+    }
+
+    fn variable(&self, name: char) -> Result<Series<R>, EvalError> {
+        if name != self.variable {
+            return Err(EvalError::UnexpectedVariable(name, self.variable));
+        }
+        match self.point {
+            ExpansionPoint::Finite(a) => {
+                let t = Series::variable_in(self.max_degree, &self.context)
+                    .with_variable(self.variable);
+                Ok(t.add(&self.constant(a.clone())?))
             }
+            // One extra term keeps 1/t known up to O(t^max_degree).
+            ExpansionPoint::Infinity => Series::variable_in(self.max_degree + 1, &self.context)
+                .with_variable(self.variable)
+                .inverse(),
+        }
+    }
+
+    fn coefficient_exponent(&self, exponent: BigRational) -> BigRational {
+        match self.point {
+            ExpansionPoint::Finite(_) => exponent,
+            ExpansionPoint::Infinity => -exponent,
+        }
+    }
+
+    /// The coefficient stays in `R`: a residue modulo a prime stands for many
+    /// rationals, so it cannot go through [`SeriesValue::coefficient`].
+    fn coefficient(
+        &self,
+        value: &Series<R>,
+        exponent: &BigRational,
+    ) -> Result<Series<R>, EvalError> {
+        let (Some(numer), Some(denom)) = (exponent.numer().to_i64(), exponent.denom().to_i64())
+        else {
+            return Err(EvalError::ExponentTooLarge);
+        };
+        let exponent = Rational64::new(numer, denom);
+        let coefficient = value.coefficient(exponent).ok_or_else(|| {
+            let missing = (exponent - value.order()).floor().to_integer() + 1;
+            EvalError::InsufficientPrecision(missing as usize)
+        })?;
+        Ok(
+            Series::constant_in(coefficient, self.max_degree, &self.context)
+                .with_variable(self.variable),
+        )
+    }
+
+    fn check_operands(&self, lhs: &Series<R>, rhs: &Series<R>) -> Result<(), EvalError> {
+        lhs.check_aligned(rhs)
+    }
+}
+
+struct Multivariate {
+    variables: Vec<char>,
+    truncation: Truncation,
+}
+
+impl Domain for Multivariate {
+    type Value = MultiSeries;
+
+    fn constant(&self, value: BigRational) -> Result<MultiSeries, EvalError> {
+        Ok(MultiSeries::constant(
+            value,
+            self.variables.clone(),
+            self.truncation.clone(),
+        ))
+    }
+
+    fn variable(&self, name: char) -> Result<MultiSeries, EvalError> {
+        let index = self
+            .variables
+            .iter()
+            .position(|&v| v == name)
+            .ok_or(EvalError::UnknownVariable(name))?;
+        Ok(MultiSeries::variable(
+            index,
+            self.variables.clone(),
+            self.truncation.clone(),
+        ))
+    }
+}
+
+/// Like [`Univariate`], but exact: the variable is `a + t` or `1/t` as a
+/// rational function of `t`.
+struct Rational<'p> {
+    variable: char,
+    point: &'p ExpansionPoint,
+    /// Degree above which powers are not expanded.
+    max_degree: usize,
+}
+
+impl Domain for Rational<'_> {
+    type Value = RationalFunction;
+
+    fn constant(&self, value: BigRational) -> Result<RationalFunction, EvalError> {
+        Ok(RationalFunction::constant(value).with_variable(self.variable))
+    }
+
+    fn variable(&self, name: char) -> Result<RationalFunction, EvalError> {
+        if name != self.variable {
+            return Err(EvalError::UnexpectedVariable(name, self.variable));
+        }
+        let t = RationalFunction::variable().with_variable(self.variable);
+        match self.point {
+            ExpansionPoint::Finite(a) => Ok(t.add(&self.constant(a.clone())?)),
+            ExpansionPoint::Infinity => t.inverse(),
+        }
+    }
+
+    fn coefficient_exponent(&self, exponent: BigRational) -> BigRational {
+        match self.point {
+            ExpansionPoint::Finite(_) => exponent,
+            ExpansionPoint::Infinity => -exponent,
+        }
+    }
+
+    fn check_power(&self, base: &RationalFunction, exponent: i64) -> Result<(), EvalError> {
+        let degree = base
+            .numerator()
+            .degree()
+            .max(base.denominator().degree())
+            .unwrap_or(0);
+        check_power_degree(degree, exponent, self.max_degree)
+    }
+}
+
+struct Exact {
+    variable: char,
+    /// Degree above which powers are not expanded.
+    max_degree: usize,
+}
 
-            let exponent_bigint = exponent_value.to_integer();
-            let exponent = exponent_bigint
-                .to_i64()
-                .ok_or(EvalError::ExponentTooLarge)?;
+impl Domain for Exact {
+    type Value = Polynomial;
 
-            base_series.powi(exponent)
+    fn constant(&self, value: BigRational) -> Result<Polynomial, EvalError> {
+        Ok(Polynomial::constant(value).with_variable(self.variable))
+    }
+
+    fn variable(&self, name: char) -> Result<Polynomial, EvalError> {
+        if name != self.variable {
+            return Err(EvalError::UnexpectedVariable(name, self.variable));
         }
-        Expr::Neg(inner) => {
-            let series = evaluate(inner, max_degree)?;
-            Ok(series.neg())
+        Ok(Polynomial::variable().with_variable(self.variable))
+    }
+
+    fn check_power(&self, base: &Polynomial, exponent: i64) -> Result<(), EvalError> {
+        check_power_degree(base.degree().unwrap_or(0), exponent, self.max_degree)
+    }
+}
+
+enum Binding<'e, V> {
+    Value(V),
+    Function {
+        params: &'e [String],
+        body: &'e Expr,
+        /// Bindings visible where the function was defined.
+        scope: Scope<'e, V>,
+    },
+}
+
+struct Frame<'e, V> {
+    name: &'e str,
+    binding: Binding<'e, V>,
+    parent: Scope<'e, V>,
+}
+
+/// Immutable chain of bindings, innermost first. Functions keep the scope
+/// they were defined in, so later shadowing does not affect them.
+struct Scope<'e, V>(Option<Rc<Frame<'e, V>>>);
+
+impl<V> Clone for Scope<'_, V> {
+    fn clone(&self) -> Self {
+        Scope(self.0.clone())
+    }
+}
+
+impl<'e, V> Scope<'e, V> {
+    fn empty() -> Self {
+        Scope(None)
+    }
+
+    fn bind(&self, name: &'e str, binding: Binding<'e, V>) -> Self {
+        Scope(Some(Rc::new(Frame {
+            name,
+            binding,
+            parent: self.clone(),
+        })))
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding<'e, V>> {
+        let mut frame = self.0.as_deref();
+        while let Some(f) = frame {
+            if f.name == name {
+                return Some(&f.binding);
+            }
+            frame = f.parent.0.as_deref();
         }
-        Expr::Sin(inner) => {
-            let series = evaluate(inner, max_degree)?;
-            series.sin()
+        None
+    }
+}
+
+/// A value together with the exact rational it equals, if it is built from
+/// literals by arithmetic. Exponents, factorials and coefficient indices use
+/// the exact value, which coefficients such as residues modulo a prime or
+/// floats cannot represent.
+#[derive(Clone)]
+struct Tracked<V> {
+    value: V,
+    exact: Option<BigRational>,
+}
+
+impl<V: SeriesValue> Tracked<V> {
+    fn new(value: V) -> Self {
+        Tracked { value, exact: None }
+    }
+
+    fn with_exact(mut self, exact: Option<BigRational>) -> Self {
+        self.exact = exact;
+        self
+    }
+
+    /// The exact value, or else the value of a constant series.
+    fn as_constant(&self) -> Result<Option<BigRational>, EvalError> {
+        match &self.exact {
+            Some(exact) => Ok(Some(exact.clone())),
+            None => self.value.as_constant(),
         }
-        Expr::Cos(inner) => {
-            let series = evaluate(inner, max_degree)?;
-            series.cos()
+    }
+
+    /// The exact value of `self` combined with `other`, if both have one.
+    fn combined(
+        &self,
+        other: &Self,
+        combine: impl FnOnce(&BigRational, &BigRational) -> Option<BigRational>,
+    ) -> Option<BigRational> {
+        combine(self.exact.as_ref()?, other.exact.as_ref()?)
+    }
+}
+
+/// Largest exponent magnitude for which powers of exact constants are kept.
+const MAX_EXACT_EXPONENT: i64 = 64;
+
+struct Evaluator<'f, D: Domain> {
+    domain: D,
+    functions: &'f FunctionRegistry<D::Value>,
+}
+
+impl<D: Domain> Evaluator<'_, D> {
+    fn evaluate(&self, expr: &Expr) -> Result<D::Value, SpannedEvalError> {
+        self.eval(expr, &Scope::empty())
+            .map(|tracked| tracked.value)
+    }
+
+    fn constant(
+        &self,
+        value: BigRational,
+        span: Span,
+    ) -> Result<Tracked<D::Value>, SpannedEvalError> {
+        let series = self
+            .domain
+            .constant(value.clone())
+            .map_err(|e| e.at(span))?;
+        Ok(Tracked::new(series).with_exact(Some(value)))
+    }
+
+    /// The error for dividing by `divisor`. Division by a non-zero constant
+    /// only fails where the coefficients cannot invert it, so the domain
+    /// reports why when converting its reciprocal.
+    fn division_error(&self, error: EvalError, divisor: &Tracked<D::Value>) -> EvalError {
+        match (&error, &divisor.exact) {
+            (EvalError::DivisionByZero, Some(value)) if !value.is_zero() => {
+                self.domain.constant(value.recip()).err().unwrap_or(error)
+            }
+            _ => error,
         }
-        Expr::Exp(inner) => {
-            let series = evaluate(inner, max_degree)?;
-            series.exp()
+    }
+
+    fn eval<'e>(
+        &self,
+        expr: &'e Expr,
+        scope: &Scope<'e, Tracked<D::Value>>,
+    ) -> Result<Tracked<D::Value>, SpannedEvalError> {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Num(value) => self.constant(value.clone(), span),
+            ExprKind::Variable(c) => self
+                .domain
+                .variable(*c)
+                .map(Tracked::new)
+                .map_err(|e| e.at(span)),
+            ExprKind::Name(name) => match scope.lookup(name) {
+                Some(Binding::Value(value)) => Ok(value.clone()),
+                Some(Binding::Function { .. }) => {
+                    Err(EvalError::FunctionUsedAsValue(name.clone()).at(span))
+                }
+                None => Err(EvalError::UndefinedName(name.clone()).at(span)),
+            },
+            ExprKind::Let { name, value, body } => {
+                let value = self.eval(value, scope)?;
+                self.eval(body, &scope.bind(name, Binding::Value(value)))
+            }
+            ExprKind::LetFunction {
+                name,
+                params,
+                value,
+                body,
+            } => {
+                let function = Binding::Function {
+                    params,
+                    body: value,
+                    scope: scope.clone(),
+                };
+                self.eval(body, &scope.bind(name, function))
+            }
+            ExprKind::Call(name, args) => {
+                let (params, body, definition_scope) = match scope.lookup(name) {
+                    Some(Binding::Function {
+                        params,
+                        body,
+                        scope,
+                    }) => (*params, *body, scope.clone()),
+                    Some(Binding::Value(_)) => {
+                        return Err(EvalError::NotAFunction(name.clone()).at(span));
+                    }
+                    None => return self.call_registered(name, args, scope, span),
+                };
+
+                if params.len() != args.len() {
+                    return Err(
+                        EvalError::ArityMismatch(name.clone(), params.len(), args.len()).at(span),
+                    );
+                }
+
+                let mut call_scope = definition_scope;
+                for (param, arg) in params.iter().zip(args) {
+                    let value = self.eval(arg, scope)?;
+                    call_scope = call_scope.bind(param, Binding::Value(value));
+                }
+                self.eval(body, &call_scope)
+            }
+            ExprKind::Add(lhs, rhs) => {
+                let left = self.eval(lhs, scope)?;
+                let right = self.eval(rhs, scope)?;
+                self.domain
+                    .check_operands(&left.value, &right.value)
+                    .map_err(|e| e.at(span))?;
+                let exact = left.combined(&right, |a, b| Some(a + b));
+                Ok(Tracked::new(left.value.add(&right.value)).with_exact(exact))
+            }
+            ExprKind::Sub(lhs, rhs) => {
+                let left = self.eval(lhs, scope)?;
+                let right = self.eval(rhs, scope)?;
+                self.domain
+                    .check_operands(&left.value, &right.value)
+                    .map_err(|e| e.at(span))?;
+                let exact = left.combined(&right, |a, b| Some(a - b));
+                Ok(Tracked::new(left.value.sub(&right.value)).with_exact(exact))
+            }
+            ExprKind::Mul(lhs, rhs) => {
+                let left = self.eval(lhs, scope)?;
+                let right = self.eval(rhs, scope)?;
+                self.domain
+                    .check_operands(&left.value, &right.value)
+                    .map_err(|e| e.at(span))?;
+                let exact = left.combined(&right, |a, b| Some(a * b));
+                Ok(Tracked::new(left.value.mul(&right.value)).with_exact(exact))
+            }
+            ExprKind::Div(lhs, rhs) => {
+                let left = self.eval(lhs, scope)?;
+                let right = self.eval(rhs, scope)?;
+                let quotient = left
+                    .value
+                    .div(&right.value)
+                    .map_err(|e| self.division_error(e, &right).at(rhs.span))?;
+                let exact = left.combined(&right, |a, b| (!b.is_zero()).then(|| a / b));
+                Ok(Tracked::new(quotient).with_exact(exact))
+            }
+            ExprKind::Pow(base, exponent) => {
+                let base_series = self.eval(base, scope)?;
+                let exponent_series = self.eval(exponent, scope)?;
+
+                // f^g = exp(g log f), which needs f(0) = 1 to stay rational.
+                let exponent_value = exponent_series
+                    .as_constant()
+                    .map_err(|e| e.at(exponent.span))?;
+                let Some(exponent_value) = exponent_value else {
+                    let log_base = base_series.value.log().map_err(|e| match e {
+                        EvalError::LogRequiresUnitConstant => {
+                            EvalError::SeriesExponentRequiresUnitBase.at(base.span)
+                        }
+                        e => e.at(span),
+                    })?;
+                    return exponent_series
+                        .value
+                        .mul(&log_base)
+                        .exp()
+                        .map(Tracked::new)
+                        .map_err(|e| e.at(span));
+                };
+                let exact = base_series.exact.as_ref().and_then(|value| {
+                    let small = exponent_value.numer().abs() <= BigInt::from(MAX_EXACT_EXPONENT);
+                    let defined = !value.is_zero() || exponent_value.is_positive();
+                    (small && defined).then(|| rational_power(value, &exponent_value).ok())?
+                });
+                if !exponent_value.is_integer() {
+                    return base_series
+                        .value
+                        .powr(&exponent_value)
+                        .map(|power| Tracked::new(power).with_exact(exact))
+                        .map_err(|e| e.at(span));
+                }
+
+                let exponent_bigint = exponent_value.to_integer();
+                let exponent_i64 = exponent_bigint
+                    .to_i64()
+                    .ok_or_else(|| EvalError::ExponentTooLarge.at(exponent.span))?;
+                self.domain
+                    .check_power(&base_series.value, exponent_i64)
+                    .map_err(|e| e.at(span))?;
+
+                base_series
+                    .value
+                    .powi(exponent_i64)
+                    .map(|power| Tracked::new(power).with_exact(exact))
+                    .map_err(|e| self.division_error(e, &base_series).at(span))
+            }
+            ExprKind::Neg(inner) => {
+                let series = self.eval(inner, scope)?;
+                let exact = series.exact.as_ref().map(|value| -value);
+                Ok(Tracked::new(series.value.neg()).with_exact(exact))
+            }
+            ExprKind::Coefficient {
+                variable,
+                degree,
+                body,
+            } => {
+                let exponent = self
+                    .eval(degree, scope)?
+                    .as_constant()
+                    .map_err(|e| e.at(degree.span))?
+                    .ok_or_else(|| EvalError::InvalidCoefficientIndex.at(degree.span))?;
+                let index = self.domain.coefficient_exponent(exponent);
+                if let Some(c) = variable {
+                    self.domain.variable(*c).map_err(|e| e.at(span))?;
+                }
+
+                let series = self.eval(body, scope)?;
+                self.domain
+                    .coefficient(&series.value, &index)
+                    .map(Tracked::new)
+                    .map_err(|e| e.at(span))
+            }
+            ExprKind::Factorial(inner) => {
+                let series = self.eval(inner, scope)?;
+
+                let value = series
+                    .as_constant()
+                    .map_err(|e| e.at(inner.span))?
+                    .ok_or_else(|| EvalError::NonConstantFactorial.at(inner.span))?;
+                if !value.is_integer() || value.is_negative() {
+                    return Err(EvalError::NonIntegerFactorial.at(inner.span));
+                }
+
+                let n = value
+                    .to_integer()
+                    .to_u32()
+                    .filter(|&n| n <= MAX_FACTORIAL)
+                    .ok_or_else(|| EvalError::FactorialTooLarge.at(inner.span))?;
+
+                self.constant(factorial(n), span)
+            }
         }
-        Expr::Log(inner) => {
-            let series = evaluate(inner, max_degree)?;
-            series.log()
+    }
+
+    fn call_registered<'e>(
+        &self,
+        name: &str,
+        args: &'e [Expr],
+        scope: &Scope<'e, Tracked<D::Value>>,
+        span: Span,
+    ) -> Result<Tracked<D::Value>, SpannedEvalError> {
+        let function = self
+            .functions
+            .get(name)
+            .ok_or_else(|| EvalError::UndefinedName(name.to_string()).at(span))?;
+        let required = function.arity() - function.optional_arguments();
+        if !(required..=function.arity()).contains(&args.len()) {
+            return Err(
+                EvalError::ArityMismatch(name.to_string(), function.arity(), args.len()).at(span),
+            );
         }
+
+        let values = args
+            .iter()
+            .map(|arg| self.eval(arg, scope).map(|tracked| tracked.value))
+            .collect::<Result<Vec<_>, _>>()?;
+        function
+            .call(&values)
+            .map(Tracked::new)
+            .map_err(|e| e.at(span))
+    }
+}
+
+/// Largest integer whose factorial is computed.
+const MAX_FACTORIAL: u32 = 10_000;
+
+fn factorial(n: u32) -> BigRational {
+    let mut result = BigInt::one();
+    for k in 2..=n {
+        result *= BigInt::from(k);
     }
+    BigRational::from_integer(result)
 }
 
 #[cfg(test)]
@@ -88,7 +1430,7 @@ mod tests {
     use super::*;
     use crate::{parser, tokenizer};
 
-    fn evaluate_str(expr: &str, max_degree: usize) -> Result<Series, EvalError> {
+    fn evaluate_str(expr: &str, max_degree: usize) -> Result<Series, SpannedEvalError> {
         let tokens = tokenizer::tokenize(expr).expect("tokenize");
         let ast = parser::parse(&tokens).expect("parse");
         evaluate(&ast, max_degree)
@@ -101,14 +1443,448 @@ mod tests {
     }
 
     #[test]
-    fn rejects_non_integer_exponent() {
-        let error = evaluate_str("(1 + x)^(x)", 3).unwrap_err();
-        assert_eq!(error, EvalError::NonIntegerExponent);
+    fn evaluates_series_exponents() {
+        let series = evaluate_str("(1 + x)^x", 4).expect("evaluate");
+        assert_eq!(
+            format!("{}", series),
+            "1 + x^2 - 1/2 x^3 + 5/6 x^4 + O(x^5)"
+        );
+
+        let error = evaluate_str("(2 + x)^(x)", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::SeriesExponentRequiresUnitBase);
+        assert_eq!(error.span, Span::new(0, 7));
+    }
+
+    #[test]
+    fn evaluates_rational_powers() {
+        let series = evaluate_str("(4 + x)^(1/2)", 3).expect("evaluate");
+        assert_eq!(
+            format!("{}", series),
+            "2 + 1/4 x - 1/64 x^2 + 1/512 x^3 + O(x^4)"
+        );
+
+        let series = evaluate_str("(1 - 9x)^(-2/3)", 2).expect("evaluate");
+        assert_eq!(format!("{}", series), "1 + 6 x + 45 x^2 + O(x^3)");
+
+        let error = evaluate_str("(2 + x)^(1/2)", 3).unwrap_err();
+        assert_eq!(
+            error.error,
+            EvalError::IrrationalPower("2".to_string(), "1/2".to_string())
+        );
+        assert_eq!(
+            error.to_string(),
+            "Constant term 2 raised to 1/2 is not rational"
+        );
+        assert_eq!(error.span, Span::new(0, 13));
+
+        let error = evaluate_str("(-1 + x)^(1/2)", 3).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Constant term -1 raised to 1/2 is not rational"
+        );
+
+        let error = evaluate_str("compose(x^(1/2), x)", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::FractionalExponents("compose"));
+    }
+
+    #[test]
+    fn evaluates_factorial_of_constant() {
+        let series = evaluate_str("5! + 0!x", 2).expect("evaluate");
+        assert_eq!(format!("{}", series), "120 + x + O(x^3)");
+    }
+
+    #[test]
+    fn factorial_binds_tighter_than_power() {
+        let series = evaluate_str("2^3! - 3!^2", 0).expect("evaluate");
+        assert_eq!(format!("{}", series), "28 + O(x^1)");
+    }
+
+    #[test]
+    fn rejects_factorial_of_series() {
+        let error = evaluate_str("(1 + x)!", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::NonConstantFactorial);
+    }
+
+    #[test]
+    fn rejects_factorial_of_non_integer() {
+        let error = evaluate_str("(1/2)!", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::NonIntegerFactorial);
+
+        let error = evaluate_str("(-1)!", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::NonIntegerFactorial);
+    }
+
+    #[test]
+    fn rejects_huge_factorials() {
+        let error = evaluate_str("4000000000!", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::FactorialTooLarge);
+    }
+
+    #[test]
+    fn errors_point_at_offending_subexpression() {
+        let error = evaluate_str("1 + 1/(x - x)", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::DivisionByZero);
+        assert_eq!(error.span, Span::new(6, 13));
+
+        let error = evaluate_str("exp(1 + x)", 3).unwrap_err();
+        assert_eq!(error.span, Span::new(0, 10));
+    }
+
+    #[test]
+    fn detects_series_variable() {
+        let series = evaluate_str("1 / (1 - q)", 2).expect("evaluate");
+        assert_eq!(format!("{}", series), "1 + q + q^2 + O(q^3)");
+
+        let series = evaluate_str("2 + 3", 1).expect("evaluate");
+        assert_eq!(format!("{}", series), "5 + O(x^2)");
+    }
+
+    #[test]
+    fn rejects_mixed_variables() {
+        let error = evaluate_str("t + z^2", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::MultipleVariables('t', 'z'));
+        assert_eq!(error.span, Span::new(4, 5));
+    }
+
+    #[test]
+    fn explicit_variable_rejects_others() {
+        let tokens = tokenizer::tokenize("exp(z)").expect("tokenize");
+        let ast = parser::parse(&tokens).expect("parse");
+
+        let series = evaluate_in(&ast, 'z', 2, &FunctionRegistry::standard()).expect("evaluate");
+        assert_eq!(format!("{}", series), "1 + z + 1/2 z^2 + O(z^3)");
+
+        let error = evaluate_in(&ast, 't', 2, &FunctionRegistry::standard()).unwrap_err();
+        assert_eq!(error.error, EvalError::UnexpectedVariable('z', 't'));
+    }
+
+    #[test]
+    fn evaluates_bivariate_series() {
+        let tokens = tokenizer::tokenize("1/(1 - x - x y)").expect("tokenize");
+        let ast = parser::parse(&tokens).expect("parse");
+        let variables = collect_variables(&ast);
+        assert_eq!(variables, vec!['x', 'y']);
+
+        let series = evaluate_multivariate(
+            &ast,
+            &variables,
+            &Truncation::TotalDegree(3),
+            &FunctionRegistry::standard(),
+        )
+        .expect("evaluate");
+        assert_eq!(
+            format!("{}", series),
+            "1 + x + x^2 + x y + x^3 + 2 x^2 y + O((x, y)^4)"
+        );
+    }
+
+    #[test]
+    fn per_variable_bounds_must_match_variables() {
+        let tokens = tokenizer::tokenize("x + y").expect("tokenize");
+        let ast = parser::parse(&tokens).expect("parse");
+        let error = evaluate_multivariate(
+            &ast,
+            &['x', 'y'],
+            &Truncation::PerVariable(vec![2]),
+            &FunctionRegistry::standard(),
+        )
+        .unwrap_err();
+        assert_eq!(error.error, EvalError::TruncationArity(2, 1));
+    }
+
+    #[test]
+    fn evaluates_let_bindings() {
+        let series = evaluate_str("let g = 1/(1-x-x^2); g^2 + g", 4).expect("evaluate");
+        assert_eq!(
+            format!("{}", series),
+            "2 + 3 x + 7 x^2 + 13 x^3 + 25 x^4 + O(x^5)"
+        );
+    }
+
+    #[test]
+    fn evaluates_user_functions() {
+        let series = evaluate_str("f(t) = 1/(1-t); f(x^2)*f(x^3)", 6).expect("evaluate");
+        assert_eq!(
+            format!("{}", series),
+            "1 + x^2 + x^3 + x^4 + x^5 + 2 x^6 + O(x^7)"
+        );
+
+        let series = evaluate_str("let f(a, b) = a - b; f(x, 2)", 1).expect("evaluate");
+        assert_eq!(format!("{}", series), "-2 + x + O(x^2)");
+    }
+
+    #[test]
+    fn functions_capture_definition_scope() {
+        let series = evaluate_str("let a = 1; f(t) = t + a; let a = 2; f(a)", 0).expect("evaluate");
+        assert_eq!(format!("{}", series), "3 + O(x^1)");
+    }
+
+    #[test]
+    fn reports_undefined_names_and_arity() {
+        let error = evaluate_str("let g = x; g + foo", 2).unwrap_err();
+        assert_eq!(error.error, EvalError::UndefinedName("foo".to_string()));
+        assert_eq!(error.span, Span::new(15, 18));
+
+        let error = evaluate_str("f(t) = t; f(x, x)", 2).unwrap_err();
+        assert_eq!(error.error, EvalError::ArityMismatch("f".to_string(), 1, 2));
+
+        let error = evaluate_str("f(t) = t; f + 1", 2).unwrap_err();
+        assert_eq!(error.error, EvalError::FunctionUsedAsValue("f".to_string()));
+    }
+
+    #[test]
+    fn reports_self_referencing_definitions() {
+        let error = evaluate_str("let a = a; a", 2).unwrap_err();
+        assert_eq!(error.error, EvalError::UndefinedName("a".to_string()));
+        assert_eq!(error.span, Span::new(8, 9));
+
+        let error = evaluate_str("f(t) = f(t); f(x)", 2).unwrap_err();
+        assert_eq!(error.error, EvalError::UndefinedName("f".to_string()));
+        assert_eq!(error.span, Span::new(7, 8));
+    }
+
+    #[test]
+    fn calls_registered_functions() {
+        use crate::functions::SeriesFunction;
+
+        struct SumOfSquares;
+
+        impl SeriesFunction for SumOfSquares {
+            fn name(&self) -> &str {
+                "sumsq"
+            }
+
+            fn arity(&self) -> usize {
+                2
+            }
+
+            fn call(&self, args: &[Series]) -> Result<Series, EvalError> {
+                Ok(args[0].mul(&args[0]).add(&args[1].mul(&args[1])))
+            }
+        }
+
+        let mut functions = FunctionRegistry::standard();
+        functions.register(SumOfSquares);
+        let tokens = tokenizer::tokenize("sumsq(sin(x), cos(x))").expect("tokenize");
+        let ast = parser::parse_with(&tokens, &functions).expect("parse");
+        let series = evaluate_in(&ast, 'x', 4, &functions).expect("evaluate");
+        assert_eq!(format!("{}", series), "1 + O(x^5)");
+
+        let error = evaluate_str("exp(x, x)", 2).unwrap_err();
+        assert_eq!(
+            error.error,
+            EvalError::ArityMismatch("exp".to_string(), 1, 2)
+        );
+        assert_eq!(error.span, Span::new(0, 9));
+    }
+
+    #[test]
+    fn compose_and_revert_are_univariate() {
+        let error = evaluate_str("revert(1 + x)", 3).unwrap_err();
+        assert_eq!(error.error, EvalError::NotReversible);
+        assert_eq!(error.span, Span::new(0, 13));
+
+        let tokens = tokenizer::tokenize("revert(x + y)").expect("tokenize");
+        let ast = parser::parse(&tokens).expect("parse");
+        let error = evaluate_multivariate(
+            &ast,
+            &['x', 'y'],
+            &Truncation::TotalDegree(2),
+            &FunctionRegistry::standard(),
+        )
+        .unwrap_err();
+        assert_eq!(error.error, EvalError::UnivariateOnly("revert"));
+    }
+
+    #[test]
+    fn detects_exact_polynomials() {
+        let polynomial = |expr: &str| {
+            let tokens = tokenizer::tokenize(expr).expect("tokenize");
+            let ast = parser::parse(&tokens).expect("parse");
+            evaluate_polynomial(&ast, 'x', &FunctionRegistry::standard())
+        };
+
+        let result = polynomial("let p = (1 + x)^2; D(p) * p / 2").expect("evaluate");
+        assert_eq!(format!("{}", result), "1 + 3 x + 3 x^2 + x^3");
+        assert_eq!(
+            polynomial("1/(1 - x)").unwrap_err().error,
+            EvalError::NotPolynomial("division")
+        );
+        assert_eq!(
+            polynomial("sin(x)").unwrap_err().error,
+            EvalError::NotPolynomial("sin")
+        );
+    }
+
+    #[test]
+    fn leaves_large_powers_to_series() {
+        let polynomial = |expr: &str, max_degree| {
+            let tokens = tokenizer::tokenize(expr).expect("tokenize");
+            let ast = parser::parse(&tokens).expect("parse");
+            evaluate_small_polynomial(&ast, 'x', max_degree, &FunctionRegistry::standard())
+        };
+
+        let result = polynomial("(1 + x)^64", 3).expect("evaluate");
+        assert_eq!(result.degree(), Some(64));
+        let result = polynomial("let p = (1 + x)^10; p^10", 100).expect("evaluate");
+        assert_eq!(result.degree(), Some(100));
+        assert_eq!(
+            polynomial("(1 + 2x)^2000/(1 + x)^2000", 5)
+                .unwrap_err()
+                .error,
+            EvalError::ExponentTooLarge
+        );
+        assert_eq!(
+            polynomial("let p = (1 + x)^10; p^10", 5).unwrap_err().error,
+            EvalError::ExponentTooLarge
+        );
+    }
+
+    #[test]
+    fn extracts_exact_coefficients() {
+        let series = evaluate_str("[x^10] 1/(1-x-x^2)", 0).expect("evaluate");
+        assert_eq!(format!("{}", series), "89 + O(x^1)");
+
+        // Too far out for the series, so it comes from the closed form.
+        let series = evaluate_str("[x^2000] 1/(1-x)^2", 0).expect("evaluate");
+        assert_eq!(format!("{}", series), "2001 + O(x^1)");
+
+        let series = evaluate_str("coeff(D(D(exp(2x))), 5) * x", 2).expect("evaluate");
+        assert_eq!(format!("{}", series), "16/15 x + O(x^3)");
+
+        let series = evaluate_str("[x^(3/2)] (1 + x)^(1/2) x^(1/2)", 2).expect("evaluate");
+        assert_eq!(format!("{}", series), "1/2 + O(x^3)");
+
+        let error = evaluate_str("[x^x] x", 2).unwrap_err();
+        assert_eq!(error.error, EvalError::InvalidCoefficientIndex);
+        assert_eq!(error.span, Span::new(3, 4));
+
+        let error = evaluate_str("[x^2] 1/(1-t)", 2).unwrap_err();
+        assert_eq!(error.error, EvalError::MultipleVariables('x', 't'));
+    }
+
+    #[test]
+    fn evaluates_modulo_a_prime() {
+        let modular = |expr: &str, modulus: u64, max_degree: usize| {
+            let tokens = tokenizer::tokenize(expr).expect("tokenize");
+            let ast = parser::parse(&tokens).expect("parse");
+            evaluate_modular(
+                &ast,
+                'x',
+                modulus,
+                max_degree,
+                &FunctionRegistry::standard(),
+            )
+        };
+
+        let series = modular("1/(1-x-x^2)", 7, 6).expect("evaluate");
+        assert_eq!(
+            format!("{}", series),
+            "1 + x + 2 x^2 + 3 x^3 + 5 x^4 + x^5 + 6 x^6 + O(x^7)"
+        );
+
+        let series = modular("[x^3] exp(2x)/x", 998244353, 3).expect("evaluate");
+        assert_eq!(format!("{}", series), "665496236 + O(x^4)");
+
+        let error = modular("1 + x/14", 7, 3).unwrap_err();
+        assert_eq!(error.error, EvalError::NotInvertible("14".to_string(), 7));
+        assert_eq!(error.span, Span::new(6, 8));
+
+        let error = modular("1/(1-x)", 91, 3).unwrap_err();
+        assert_eq!(error.error, EvalError::InvalidModulus(91));
+
+        // A residue is not the rational it stands for, so it cannot be used
+        // as an exponent or a factorial argument.
+        let error = modular("x^([x^1] x/2)", 998244353, 3).unwrap_err();
+        assert_eq!(
+            error.error,
+            EvalError::NoRationalValue("499122177".to_string())
+        );
+        assert_eq!(error.span, Span::new(2, 13));
+        let error = modular("([x^0] 3)!", 7, 3).unwrap_err();
+        assert_eq!(error.error, EvalError::NoRationalValue("3".to_string()));
+    }
+
+    #[test]
+    fn multimodular_evaluation_matches_exact_one() {
+        let cases = [
+            ("exp(x) * log(1-x)^2", "0"),
+            ("(9/4 + x)^(1/2) / (1 - 3x)^3", "0"),
+            ("sqrt(x + x^2) / (1 - 1000000x)", "0"),
+            ("[x^7] exp(sin(x)) / cos(x)", "0"),
+            ("log(x)", "1"),
+            ("exp(1/x)", "inf"),
+            ("log(2 + x)", "0"),
+            ("1/(x - x)", "0"),
+            ("x^([x^1] x/2)", "0"),
+            ("1/x - 1/x", "0"),
+            ("1/(1 - x - x^2)", "inf"),
+            ("1/(1 - x)^64 + 1/(1 + 2x)^64", "0"),
+            ("[x^2000] 1/(1 - x)^2", "0"),
+        ];
+        for (expr, point) in cases {
+            let point: ExpansionPoint = point.parse().expect("point");
+            let tokens = tokenizer::tokenize(expr).expect("tokenize");
+            let ast = parser::parse(&tokens).expect("parse");
+            let functions = FunctionRegistry::standard();
+            assert_eq!(
+                evaluate_multimodular(&ast, 'x', &point, 12),
+                evaluate_around(&ast, 'x', &point, 12, &functions),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn evaluates_with_floating_point_coefficients() {
+        let float = |expr: &str, max_degree: usize| {
+            let tokens = tokenizer::tokenize(expr).expect("tokenize");
+            let ast = parser::parse(&tokens).expect("parse");
+            evaluate_over::<f64>(&ast, 'x', &(), max_degree, &FunctionRegistry::standard())
+        };
+
+        let series = float("exp(x/2)", 3).expect("evaluate");
+        assert_eq!(
+            format!("{}", series),
+            "1 + 0.5 x + 0.125 x^2 + 0.020833333333333332 x^3 + O(x^4)"
+        );
+
+        // The exponent stays exactly 1/3 rather than its nearest float.
+        let series = float("(8 - x)^(1/3)", 1).expect("evaluate");
+        assert_eq!(series.coeffs()[0], 2.0);
+        assert!((series.coeffs()[1] + 1.0 / 12.0).abs() < 1e-12);
+
+        let error = float("(-4 + x)^(1/2)", 1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Constant term -4 raised to 1/2 is not real"
+        );
     }
 
     #[test]
     fn rejects_large_exponent() {
         let error = evaluate_str("(1 + x)^(100000000000000000000)", 3).unwrap_err();
-        assert_eq!(error, EvalError::ExponentTooLarge);
+        assert_eq!(error.error, EvalError::ExponentTooLarge);
+    }
+
+    #[test]
+    fn rejects_huge_ramification() {
+        for input in ["x^(1/1000000000)", "x^(1/997) + x^(1/991)", "x^(1/997) * x^(1/991)"] {
+            let error = evaluate_str(input, 2).unwrap_err();
+            assert_eq!(error.error, EvalError::ExponentTooLarge, "{input}");
+        }
+    }
+
+    #[test]
+    fn rejects_poles_beyond_the_working_degree() {
+        for input in ["1/x^1000000000", "x^(-1000000000)", "1/x^4611686018427387904"] {
+            let error = evaluate_str(input, 3).unwrap_err();
+            assert_eq!(error.error, EvalError::ExponentTooLarge, "{input}");
+        }
+        assert_eq!(
+            evaluate_str("1/x^1000", 1).expect("evaluate").to_string(),
+            "x^-1000 + O(x^2)"
+        );
     }
 }
@@ -0,0 +1,747 @@
+//! Factorization of polynomials over the rationals.
+//!
+//! A polynomial is first split into square-free parts (Yun's algorithm).
+//! Each part is then factored with Zassenhaus' algorithm: factor it modulo
+//! a small prime `p`, Hensel-lift the factors to a power of `p` beyond the
+//! coefficient bound, and recombine the lifted factors into the true
+//! factors over the integers.
+//!
+//! Recombination tries subsets of the modular factors, so it is exponential
+//! in their number. Several primes are sampled and the one giving the fewest
+//! factors is used. The degrees of the factors modulo every sampled prime
+//! restrict the degrees a true factor can have, and a subset is only tried by
+//! trial division once its degree and constant term are consistent.
+
+use num_bigint::{BigInt, BigUint};
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::fmt;
+
+use crate::modint::{inverse_mod, is_prime};
+use crate::polynomial::Polynomial;
+use crate::series::format_rational;
+
+/// A polynomial written as `content * prod factor^multiplicity`.
+///
+/// Factors are irreducible over the rationals, have coprime integer
+/// coefficients and a positive leading coefficient, and are sorted by degree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Factorization {
+    content: BigRational,
+    factors: Vec<(Polynomial, usize)>,
+}
+
+impl Factorization {
+    pub fn content(&self) -> &BigRational {
+        &self.content
+    }
+
+    /// Irreducible factors with their multiplicities.
+    pub fn factors(&self) -> &[(Polynomial, usize)] {
+        &self.factors
+    }
+
+    /// Multiplies the factorization back out.
+    pub fn expand(&self) -> Polynomial {
+        self.factors.iter().fold(
+            Polynomial::constant(self.content.clone()),
+            |product, (factor, multiplicity)| {
+                let power = factor
+                    .powi(*multiplicity as i64)
+                    .expect("factor powers stay small");
+                product.mul(&power)
+            },
+        )
+    }
+}
+
+impl fmt::Display for Factorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.factors.is_empty() {
+            return write!(f, "{}", format_rational(&self.content));
+        }
+
+        if self.content == -BigRational::one() {
+            write!(f, "-")?;
+        } else if !self.content.is_one() {
+            write!(f, "{} ", format_rational(&self.content))?;
+        }
+
+        for (i, (factor, multiplicity)) in self.factors.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            let terms = factor.coeffs().iter().filter(|c| !c.is_zero()).count();
+            if terms > 1 {
+                write!(f, "({})", factor)?;
+            } else {
+                write!(f, "{}", factor)?;
+            }
+            if *multiplicity > 1 {
+                write!(f, "^{}", multiplicity)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Polynomial {
+    /// Square-free decomposition: pairwise coprime, square-free, monic
+    /// `(g, i)` such that `self` is a constant times the product of `g^i`.
+    pub fn square_free(&self) -> Vec<(Polynomial, usize)> {
+        let mut parts = Vec::new();
+        if self.degree().unwrap_or(0) == 0 {
+            return parts;
+        }
+
+        // Yun's algorithm.
+        let f = self.monic();
+        let derivative = f.derivative();
+        let common = f.gcd(&derivative);
+        let mut b = exact_quotient(&f, &common);
+        let mut d = exact_quotient(&derivative, &common).sub(&b.derivative());
+        let mut multiplicity = 1;
+        while b.degree().unwrap_or(0) > 0 {
+            let a = b.gcd(&d);
+            b = exact_quotient(&b, &a);
+            d = exact_quotient(&d, &a).sub(&b.derivative());
+            if a.degree().unwrap_or(0) > 0 {
+                parts.push((a, multiplicity));
+            }
+            multiplicity += 1;
+        }
+        parts
+    }
+
+    /// Factors `self` into irreducible polynomials over the rationals.
+    pub fn factor(&self) -> Factorization {
+        let mut factors = Vec::new();
+        for (part, multiplicity) in self.square_free() {
+            for factor in factor_square_free(primitive(&part)) {
+                let coeffs = factor.into_iter().map(BigRational::from_integer).collect();
+                let factor = Polynomial::new(coeffs).with_variable(self.variable_name());
+                factors.push((factor, multiplicity));
+            }
+        }
+        factors.sort_by(|(a, _), (b, _)| {
+            a.degree()
+                .cmp(&b.degree())
+                .then_with(|| a.coeffs().cmp(b.coeffs()))
+        });
+
+        let leading = factors
+            .iter()
+            .fold(BigRational::one(), |product, (factor, multiplicity)| {
+                product * factor.leading_coefficient().pow(*multiplicity as i32)
+            });
+        Factorization {
+            content: self.leading_coefficient() / leading,
+            factors,
+        }
+    }
+}
+
+fn exact_quotient(a: &Polynomial, b: &Polynomial) -> Polynomial {
+    a.div_rem(b).expect("divisor is non-zero").0
+}
+
+/// Integer polynomial, coefficients from the constant term up.
+type IntPoly = Vec<BigInt>;
+
+/// Polynomial over the integers modulo a small prime.
+type ModPoly = Vec<u64>;
+
+/// Positive multiple of `p` with coprime integer coefficients.
+fn primitive(p: &Polynomial) -> IntPoly {
+    let denominators = p
+        .coeffs()
+        .iter()
+        .fold(BigInt::one(), |acc, c| lcm(&acc, c.denom()));
+    let coeffs: IntPoly = p
+        .coeffs()
+        .iter()
+        .map(|c| (c * BigRational::from_integer(denominators.clone())).to_integer())
+        .collect();
+    normalize(coeffs)
+}
+
+/// Divides out the content and makes the leading coefficient positive.
+fn normalize(coeffs: IntPoly) -> IntPoly {
+    let content = coeffs.iter().fold(BigInt::zero(), |acc, c| gcd(&acc, c));
+    let content = if coeffs.last().is_some_and(Signed::is_negative) {
+        -content
+    } else {
+        content
+    };
+    coeffs.into_iter().map(|c| c / &content).collect()
+}
+
+/// Irreducible factors of a square-free primitive integer polynomial.
+fn factor_square_free(g: IntPoly) -> Vec<IntPoly> {
+    let degree = g.len() - 1;
+    if degree <= 1 {
+        return vec![g];
+    }
+
+    let lead = g[degree].clone();
+    let mut samples = (3u64..)
+        .step_by(2)
+        .filter(|&p| is_prime(p))
+        .filter_map(|p| {
+            let reduced = reduce(&g, p);
+            let square_free = reduced.len() == g.len()
+                && mod_gcd(&reduced, &mod_derivative(&reduced, p), p).len() == 1;
+            square_free.then(|| (p, factor_mod(&mod_monic(&reduced, p), p)))
+        })
+        .take(PRIME_SAMPLES);
+
+    let (mut p, mut modular) = samples
+        .next()
+        .expect("some prime keeps the polynomial square-free");
+    let mut degrees = subset_degrees(&modular, degree);
+    for (q, factors) in samples {
+        if modular.len() == 1 {
+            break;
+        }
+        degrees = intersect(&degrees, &subset_degrees(&factors, degree));
+        if factors.len() < modular.len() {
+            (p, modular) = (q, factors);
+        }
+    }
+    // Only the trivial factors are consistent with every sampled prime.
+    if modular.len() == 1 || degrees[1..degree].iter().all(|&possible| !possible) {
+        return vec![g];
+    }
+
+    // Mignotte's bound: lead times any monic factor has coefficients below
+    // |lead| 2^n |g|_2, and |g|_2 <= (n + 1) max |g_i|.
+    let max_coeff = g.iter().map(|c| c.abs()).max().expect("non-empty");
+    let bound = lead.abs() * (BigInt::one() << degree) * BigInt::from(degree + 1) * max_coeff;
+    let mut modulus = BigInt::from(p);
+    let mut exponent = 1;
+    while modulus <= &bound * 2 {
+        modulus *= p;
+        exponent += 1;
+    }
+
+    let monic = reduce_big(&scale_big(&g, &mod_inverse(&lead, &modulus)), &modulus);
+    let lifted = hensel_lift(&monic, &modular, p, exponent);
+    recombine(g, lifted, &modulus, &degrees)
+}
+
+/// Whether `a` and `b` are coprime, as shown by their gcd modulo a prime
+/// that divides neither leading coefficient: its degree is at least that of
+/// the gcd over the rationals. `false` means they may have a common factor.
+pub(crate) fn coprime_modulo_prime(a: &Polynomial, b: &Polynomial) -> bool {
+    if a.is_zero() || b.is_zero() {
+        return false;
+    }
+    let (a, b) = (primitive(a), primitive(b));
+    let p = ((1u64 << 31) + 1..)
+        .step_by(2)
+        .filter(|&p| is_prime(p))
+        .find(|&p| reduce(&a, p).len() == a.len() && reduce(&b, p).len() == b.len())
+        .expect("some prime divides neither leading coefficient");
+    mod_gcd(&reduce(&a, p), &reduce(&b, p), p).len() == 1
+}
+
+/// Number of primes [`factor_square_free`] factors modulo before picking one.
+const PRIME_SAMPLES: usize = 5;
+
+/// Which degrees in `0..=n` are the total degree of some subset of `factors`.
+fn subset_degrees(factors: &[ModPoly], n: usize) -> Vec<bool> {
+    let mut possible = vec![false; n + 1];
+    possible[0] = true;
+    for factor in factors {
+        let d = factor.len() - 1;
+        for total in (d..=n).rev() {
+            possible[total] |= possible[total - d];
+        }
+    }
+    possible
+}
+
+fn intersect(a: &[bool], b: &[bool]) -> Vec<bool> {
+    a.iter().zip(b).map(|(&x, &y)| x && y).collect()
+}
+
+/// Lifts the monic factorization `factors` of `f` modulo `p` to one modulo
+/// `p^exponent`, where `f` is monic modulo `p^exponent`.
+fn hensel_lift(f: &IntPoly, factors: &[ModPoly], p: u64, exponent: u32) -> Vec<IntPoly> {
+    let Some((first, rest)) = factors.split_first() else {
+        return Vec::new();
+    };
+    if rest.is_empty() {
+        return vec![f.clone()];
+    }
+
+    let others = rest
+        .iter()
+        .fold(vec![1], |product, factor| mod_mul(&product, factor, p));
+    let (a, b) = lift_pair(f, first, &others, p, exponent);
+    let mut lifted = vec![a];
+    lifted.extend(hensel_lift(&b, rest, p, exponent));
+    lifted
+}
+
+/// Linear Hensel lifting of `f = a b (mod p)` to `f = a' b' (mod p^exponent)`
+/// with `a' = a` and `b' = b` modulo `p`, all monic.
+fn lift_pair(f: &IntPoly, a0: &ModPoly, b0: &ModPoly, p: u64, exponent: u32) -> (IntPoly, IntPoly) {
+    let (s, t) = mod_bezout(a0, b0, p);
+    let mut a: IntPoly = a0.iter().map(|&c| BigInt::from(c)).collect();
+    let mut b: IntPoly = b0.iter().map(|&c| BigInt::from(c)).collect();
+    let mut modulus = BigInt::from(p);
+
+    for _ in 1..exponent {
+        // f - a b is divisible by the current modulus; correct a and b by
+        // multiples of it so that s a + t b = 1 absorbs the error e.
+        let error: IntPoly = sub_big(f, &mul_big(&a, &b))
+            .into_iter()
+            .map(|c| c / &modulus)
+            .collect();
+        let e = reduce(&error, p);
+        let (q, r) = mod_div_rem(&mod_mul(&t, &e, p), a0, p);
+        let correction = mod_add(&mod_mul(&s, &e, p), &mod_mul(&q, b0, p), p);
+        a = add_big(&a, &scale_big(&lift(&r), &modulus));
+        b = add_big(&b, &scale_big(&lift(&correction), &modulus));
+        modulus *= p;
+    }
+    (a, b)
+}
+
+/// Combines the lifted modular factors of `g` into its factors over the
+/// integers, trying subsets of increasing size.
+///
+/// A factor of `g` has a degree allowed by `degrees`, and its constant term
+/// times `lead / lc(factor)` divides `lead g(0)`, so subsets failing either
+/// test are skipped before the product is formed.
+fn recombine(
+    mut g: IntPoly,
+    mut lifted: Vec<IntPoly>,
+    modulus: &BigInt,
+    degrees: &[bool],
+) -> Vec<IntPoly> {
+    let mut factors = Vec::new();
+    let mut size = 1;
+    while 2 * size <= lifted.len() {
+        let lead = g.last().expect("non-zero").clone();
+        let trailing = &lead * &g[0];
+        let found = combinations(lifted.len(), size).find_map(|subset| {
+            let degree: usize = subset.iter().map(|&i| lifted[i].len() - 1).sum();
+            if !degrees[degree] {
+                return None;
+            }
+            if !trailing.is_zero() {
+                let constant = subset.iter().fold(lead.clone(), |product, &i| {
+                    symmetric_modulo(&(product * &lifted[i][0]), modulus)
+                });
+                if constant.is_zero() || !(&trailing % &constant).is_zero() {
+                    return None;
+                }
+            }
+
+            let product = subset.iter().fold(vec![lead.clone()], |product, &i| {
+                mul_big(&product, &lifted[i])
+            });
+            let candidate = normalize(symmetric(&product, modulus));
+            let quotient = divide_exactly(&g, &candidate)?;
+            Some((subset, candidate, quotient))
+        });
+
+        match found {
+            Some((subset, factor, quotient)) => {
+                factors.push(factor);
+                g = quotient;
+                for &i in subset.iter().rev() {
+                    lifted.remove(i);
+                }
+            }
+            None => size += 1,
+        }
+    }
+    factors.push(g);
+    factors
+}
+
+/// `a / b` if `b` divides `a` over the integers.
+///
+/// Long division stops at the first quotient coefficient that is not an
+/// integer, so wrong recombination candidates are rejected early.
+fn divide_exactly(a: &IntPoly, b: &IntPoly) -> Option<IntPoly> {
+    if a.len() < b.len() {
+        return None;
+    }
+    let lead = b.last().expect("divisor is non-zero");
+    let mut remainder = a.clone();
+    let mut quotient = vec![BigInt::zero(); a.len() - b.len() + 1];
+    for k in (0..quotient.len()).rev() {
+        let top = &remainder[k + b.len() - 1];
+        if !(top % lead).is_zero() {
+            return None;
+        }
+        let factor = top / lead;
+        for (i, c) in b.iter().enumerate() {
+            remainder[k + i] -= &factor * c;
+        }
+        quotient[k] = factor;
+    }
+    remainder.iter().all(Zero::is_zero).then_some(quotient)
+}
+
+/// Index sets of size `k` from `0..n`, in lexicographic order.
+fn combinations(n: usize, k: usize) -> impl Iterator<Item = Vec<usize>> {
+    let mut next = (k <= n).then(|| (0..k).collect::<Vec<_>>());
+    std::iter::from_fn(move || {
+        let current = next.take()?;
+        let mut indices = current.clone();
+        if let Some(i) = (0..k).rev().find(|&i| indices[i] < n - k + i) {
+            indices[i] += 1;
+            for j in i + 1..k {
+                indices[j] = indices[j - 1] + 1;
+            }
+            next = Some(indices);
+        }
+        Some(current)
+    })
+}
+
+/// Distinct-degree then equal-degree (Cantor-Zassenhaus) factorization of
+/// a monic square-free polynomial modulo an odd prime.
+fn factor_mod(f: &ModPoly, p: u64) -> Vec<ModPoly> {
+    let x = vec![0, 1];
+    let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+    let mut factors = Vec::new();
+    let mut rest = f.clone();
+    let mut h = mod_div_rem(&x, &rest, p).1;
+    let mut degree = 1;
+
+    while rest.len() > 2 * degree {
+        // Product of the irreducible factors of degree `degree`.
+        h = mod_pow(&h, &BigUint::from(p), &rest, p);
+        let g = mod_gcd(&mod_sub(&h, &x, p), &rest, p);
+        if g.len() > 1 {
+            rest = mod_div_rem(&rest, &g, p).0;
+            h = mod_div_rem(&h, &rest, p).1;
+            factors.extend(split_equal_degree(g, degree, p, &mut rng));
+        }
+        degree += 1;
+    }
+    if rest.len() > 1 {
+        factors.push(rest);
+    }
+    factors
+}
+
+fn split_equal_degree(g: ModPoly, degree: usize, p: u64, rng: &mut XorShift) -> Vec<ModPoly> {
+    if g.len() - 1 == degree {
+        return vec![g];
+    }
+
+    let exponent = (BigUint::from(p).pow(degree as u32) - 1u32) / 2u32;
+    loop {
+        let a = trim((0..g.len() - 1).map(|_| rng.next() % p).collect());
+        let b = mod_sub(&mod_pow(&a, &exponent, &g, p), &[1], p);
+        let c = mod_gcd(&b, &g, p);
+        if c.len() > 1 && c.len() < g.len() {
+            let other = mod_div_rem(&g, &c, p).0;
+            let mut factors = split_equal_degree(c, degree, p, rng);
+            factors.extend(split_equal_degree(other, degree, p, rng));
+            return factors;
+        }
+    }
+}
+
+/// Deterministic pseudo-random numbers for choosing splitting polynomials.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+pub(crate) fn gcd(a: &BigInt, b: &BigInt) -> BigInt {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while !b.is_zero() {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+pub(crate) fn lcm(a: &BigInt, b: &BigInt) -> BigInt {
+    a / gcd(a, b) * b
+}
+
+/// `a mod m` in `0..m`.
+fn modulo(a: &BigInt, m: &BigInt) -> BigInt {
+    let r = a % m;
+    if r.is_negative() { r + m } else { r }
+}
+
+/// Inverse of `a` modulo `m`, which must be coprime to it.
+fn mod_inverse(a: &BigInt, m: &BigInt) -> BigInt {
+    let (mut old_r, mut r) = (modulo(a, m), m.clone());
+    let (mut old_s, mut s) = (BigInt::one(), BigInt::zero());
+    while !r.is_zero() {
+        let q = &old_r / &r;
+        let next_r = &old_r - &q * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_s = &old_s - &q * &s;
+        old_s = std::mem::replace(&mut s, next_s);
+    }
+    modulo(&old_s, m)
+}
+
+fn add_big(a: &IntPoly, b: &IntPoly) -> IntPoly {
+    let zero = BigInt::zero();
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&zero) + b.get(i).unwrap_or(&zero))
+        .collect()
+}
+
+fn sub_big(a: &IntPoly, b: &IntPoly) -> IntPoly {
+    add_big(a, &b.iter().map(|c| -c).collect())
+}
+
+fn mul_big(a: &IntPoly, b: &IntPoly) -> IntPoly {
+    let mut product = vec![BigInt::zero(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+    product
+}
+
+fn scale_big(a: &IntPoly, factor: &BigInt) -> IntPoly {
+    a.iter().map(|c| c * factor).collect()
+}
+
+fn reduce_big(a: &IntPoly, m: &BigInt) -> IntPoly {
+    a.iter().map(|c| modulo(c, m)).collect()
+}
+
+/// Representatives in `(-m/2, m/2]`, so small negative coefficients survive.
+fn symmetric(a: &IntPoly, m: &BigInt) -> IntPoly {
+    a.iter().map(|c| symmetric_modulo(c, m)).collect()
+}
+
+/// `a mod m` in `(-m/2, m/2]`.
+fn symmetric_modulo(a: &BigInt, m: &BigInt) -> BigInt {
+    let r = modulo(a, m);
+    if r > m / 2 { r - m } else { r }
+}
+
+fn lift(a: &ModPoly) -> IntPoly {
+    a.iter().map(|&c| BigInt::from(c)).collect()
+}
+
+fn reduce(a: &IntPoly, p: u64) -> ModPoly {
+    let m = BigInt::from(p);
+    trim(
+        a.iter()
+            .map(|c| modulo(c, &m).to_u64().expect("reduced below p"))
+            .collect(),
+    )
+}
+
+fn trim(mut a: ModPoly) -> ModPoly {
+    while a.last() == Some(&0) {
+        a.pop();
+    }
+    a
+}
+
+fn mod_add(a: &[u64], b: &[u64], p: u64) -> ModPoly {
+    trim(
+        (0..a.len().max(b.len()))
+            .map(|i| (a.get(i).unwrap_or(&0) + b.get(i).unwrap_or(&0)) % p)
+            .collect(),
+    )
+}
+
+fn mod_sub(a: &[u64], b: &[u64], p: u64) -> ModPoly {
+    let negated: ModPoly = b.iter().map(|&c| (p - c) % p).collect();
+    mod_add(a, &negated, p)
+}
+
+fn mod_mul(a: &[u64], b: &[u64], p: u64) -> ModPoly {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut product = vec![0; a.len() + b.len() - 1];
+    for (i, &x) in a.iter().enumerate() {
+        for (j, &y) in b.iter().enumerate() {
+            product[i + j] = (product[i + j] + x * y) % p;
+        }
+    }
+    trim(product)
+}
+
+fn mod_monic(a: &[u64], p: u64) -> ModPoly {
+    match a.last() {
+        Some(&lead) => {
+            let inverse = inverse_mod(lead, p);
+            a.iter().map(|&c| c * inverse % p).collect()
+        }
+        None => Vec::new(),
+    }
+}
+
+fn mod_div_rem(a: &[u64], b: &[u64], p: u64) -> (ModPoly, ModPoly) {
+    let inverse = inverse_mod(*b.last().expect("divisor is non-zero"), p);
+    let mut remainder = a.to_vec();
+    if remainder.len() < b.len() {
+        return (Vec::new(), remainder);
+    }
+
+    let mut quotient = vec![0; remainder.len() - b.len() + 1];
+    for k in (0..quotient.len()).rev() {
+        let factor = remainder[k + b.len() - 1] * inverse % p;
+        for (i, &c) in b.iter().enumerate() {
+            remainder[k + i] = (remainder[k + i] + p - factor * c % p) % p;
+        }
+        quotient[k] = factor;
+    }
+    remainder.truncate(b.len() - 1);
+    (trim(quotient), trim(remainder))
+}
+
+/// Monic greatest common divisor modulo `p`.
+fn mod_gcd(a: &[u64], b: &[u64], p: u64) -> ModPoly {
+    let (mut a, mut b) = (trim(a.to_vec()), trim(b.to_vec()));
+    while !b.is_empty() {
+        let r = mod_div_rem(&a, &b, p).1;
+        a = b;
+        b = r;
+    }
+    mod_monic(&a, p)
+}
+
+/// `(s, t)` with `s a + t b = 1` modulo `p`, for coprime `a` and `b`.
+fn mod_bezout(a: &[u64], b: &[u64], p: u64) -> (ModPoly, ModPoly) {
+    let (mut old_r, mut r) = (a.to_vec(), b.to_vec());
+    let (mut old_s, mut s) = (vec![1], Vec::new());
+    let (mut old_t, mut t) = (Vec::new(), vec![1]);
+    while !r.is_empty() {
+        let (q, next_r) = mod_div_rem(&old_r, &r, p);
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_s = mod_sub(&old_s, &mod_mul(&q, &s, p), p);
+        old_s = std::mem::replace(&mut s, next_s);
+        let next_t = mod_sub(&old_t, &mod_mul(&q, &t, p), p);
+        old_t = std::mem::replace(&mut t, next_t);
+    }
+    // old_r is a non-zero constant; scale it to 1.
+    let inverse = [inverse_mod(old_r[0], p)];
+    (mod_mul(&old_s, &inverse, p), mod_mul(&old_t, &inverse, p))
+}
+
+fn mod_derivative(a: &[u64], p: u64) -> ModPoly {
+    trim(
+        a.iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, &c)| (k as u64 % p) * c % p)
+            .collect(),
+    )
+}
+
+/// `base^exponent` modulo `m` and `p`.
+fn mod_pow(base: &[u64], exponent: &BigUint, m: &[u64], p: u64) -> ModPoly {
+    let mut result = mod_div_rem(&[1], m, p).1;
+    let base = mod_div_rem(base, m, p).1;
+    for i in (0..exponent.bits()).rev() {
+        result = mod_div_rem(&mod_mul(&result, &result, p), m, p).1;
+        if exponent.bit(i) {
+            result = mod_div_rem(&mod_mul(&result, &base, p), m, p).1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(coeffs: &[i64]) -> Polynomial {
+        Polynomial::new(
+            coeffs
+                .iter()
+                .map(|&c| BigRational::from_integer(c.into()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn square_free_decomposition_separates_multiplicities() {
+        // (x - 1)^2 (x + 2)^3 x
+        let p = poly(&[1, -1])
+            .powi(2)
+            .unwrap()
+            .mul(&poly(&[2, 1]).powi(3).unwrap())
+            .mul(&poly(&[0, 1]));
+        let parts = p.square_free();
+        assert_eq!(
+            parts,
+            vec![(poly(&[0, 1]), 1), (poly(&[-1, 1]), 2), (poly(&[2, 1]), 3)]
+        );
+    }
+
+    #[test]
+    fn factors_over_the_rationals() {
+        let p = poly(&[-1, 0, 0, 0, 0, 0, 1]); // x^6 - 1
+        let factorization = p.factor();
+        assert_eq!(
+            format!("{}", factorization),
+            "(-1 + x) (1 + x) (1 - x + x^2) (1 + x + x^2)"
+        );
+        assert_eq!(factorization.expand(), p);
+
+        let p = poly(&[4, 0, -4]).mul(&poly(&[1, 0, 1]).powi(2).unwrap());
+        assert_eq!(format!("{}", p.factor()), "-4 (-1 + x) (1 + x) (1 + x^2)^2");
+
+        let half = BigRational::new(1.into(), 2.into());
+        assert_eq!(
+            format!("{}", poly(&[0, 1, 2]).scale(&half).factor()),
+            "1/2 x (1 + 2 x)"
+        );
+    }
+
+    #[test]
+    fn irreducible_modulo_no_prime_needs_recombination() {
+        // x^4 + 1 splits modulo every prime but is irreducible over Q.
+        let p = poly(&[1, 0, 0, 0, 1]);
+        assert_eq!(p.factor().factors(), &[(p.clone(), 1)]);
+
+        // Swinnerton-Dyer style: (x^4 - 10 x^2 + 1)(x^2 - 2)
+        let p = poly(&[1, 0, -10, 0, 1]).mul(&poly(&[-2, 0, 1]));
+        assert_eq!(format!("{}", p.factor()), "(-2 + x^2) (1 - 10 x^2 + x^4)");
+    }
+
+    #[test]
+    fn factors_cyclotomic_products() {
+        // x^n - 1 is the product of the cyclotomic polynomials for d | n, and
+        // splits into many more factors modulo any prime.
+        for n in [72usize, 120] {
+            let mut coeffs = vec![0; n + 1];
+            coeffs[0] = -1;
+            coeffs[n] = 1;
+            let p = poly(&coeffs);
+            let factorization = p.factor();
+            let divisors = (1..=n).filter(|d| n % d == 0).count();
+            assert_eq!(factorization.factors().len(), divisors);
+            assert_eq!(factorization.expand(), p);
+        }
+    }
+
+    #[test]
+    fn constants_have_no_factors() {
+        assert_eq!(format!("{}", poly(&[-3]).factor()), "-3");
+        assert_eq!(format!("{}", Polynomial::zero().factor()), "0");
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use crate::evaluator::{EvalError, SeriesValue};
+use crate::series::Series;
+use num_rational::BigRational;
+
+/// A function that can be called by name from expressions, e.g. `sin(x)`.
+///
+/// `V` is the series type the function operates on; functions written
+/// against [`SeriesValue`] work for every kind of evaluation.
+pub trait SeriesFunction<V = Series> {
+    fn name(&self) -> &str;
+
+    /// Number of arguments the function expects.
+    fn arity(&self) -> usize {
+        1
+    }
+
+    /// Number of trailing arguments that may be left out.
+    fn optional_arguments(&self) -> usize {
+        0
+    }
+
+    /// Applies the function. `args` has at most [`SeriesFunction::arity`]
+    /// elements, and at most [`SeriesFunction::optional_arguments`] fewer.
+    fn call(&self, args: &[V]) -> Result<V, EvalError>;
+}
+
+/// Single-argument function backed by a plain function pointer.
+pub struct UnaryFunction<V> {
+    name: &'static str,
+    apply: fn(&V) -> Result<V, EvalError>,
+}
+
+impl<V> UnaryFunction<V> {
+    pub fn new(name: &'static str, apply: fn(&V) -> Result<V, EvalError>) -> Self {
+        Self { name, apply }
+    }
+}
+
+/// Two-argument function backed by a plain function pointer.
+pub struct BinaryFunction<V> {
+    name: &'static str,
+    apply: fn(&V, &V) -> Result<V, EvalError>,
+}
+
+impl<V> BinaryFunction<V> {
+    pub fn new(name: &'static str, apply: fn(&V, &V) -> Result<V, EvalError>) -> Self {
+        Self { name, apply }
+    }
+}
+
+impl<V> SeriesFunction<V> for BinaryFunction<V> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn call(&self, args: &[V]) -> Result<V, EvalError> {
+        (self.apply)(&args[0], &args[1])
+    }
+}
+
+impl<V> SeriesFunction<V> for UnaryFunction<V> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn call(&self, args: &[V]) -> Result<V, EvalError> {
+        (self.apply)(&args[0])
+    }
+}
+
+/// Functions available to the parser and evaluator, looked up by name.
+pub struct FunctionRegistry<V = Series> {
+    functions: BTreeMap<String, Box<dyn SeriesFunction<V>>>,
+}
+
+impl<V> FunctionRegistry<V> {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self {
+            functions: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `function`, replacing any function registered under the same name.
+    pub fn register(&mut self, function: impl SeriesFunction<V> + 'static) {
+        self.functions
+            .insert(function.name().to_string(), Box::new(function));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn SeriesFunction<V>> {
+        self.functions.get(name).map(|f| f.as_ref())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Registered function names in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+}
+
+impl<V> Default for FunctionRegistry<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn sqrt<V: SeriesValue>(series: &V) -> Result<V, EvalError> {
+    series.powr(&BigRational::new(1.into(), 2.into()))
+}
+
+/// `factor(f)` is `f` itself; front ends show a top-level call in factored
+/// form, see [`crate::evaluator::is_factor_query`].
+fn factor<V: SeriesValue>(series: &V) -> Result<V, EvalError> {
+    Ok(series.clone())
+}
+
+/// `int(f, c)` is the antiderivative of `f` with value `c` at the expansion
+/// point, and `int(f)` the one with value 0.
+struct Integral;
+
+impl<V: SeriesValue> SeriesFunction<V> for Integral {
+    fn name(&self) -> &str {
+        "int"
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn optional_arguments(&self) -> usize {
+        1
+    }
+
+    fn call(&self, args: &[V]) -> Result<V, EvalError> {
+        let integral = args[0].integral()?;
+        match args.get(1) {
+            Some(constant) if constant.as_constant() == Ok(None) => {
+                Err(EvalError::NonConstantIntegrationConstant)
+            }
+            Some(constant) => Ok(integral.add(constant)),
+            None => Ok(integral),
+        }
+    }
+}
+
+impl<V: SeriesValue + 'static> FunctionRegistry<V> {
+    /// Registry with the built-in functions `sin`, `cos`, `exp`, `log`,
+    /// `sqrt`, `compose(f, g)`, `revert(f)`, `D(f)`, `int(f)`, `int(f, c)` and
+    /// `factor(f)`.
+    pub fn standard() -> Self {
+        let mut registry = Self::new();
+        registry.register(UnaryFunction::new("sin", V::sin));
+        registry.register(UnaryFunction::new("cos", V::cos));
+        registry.register(UnaryFunction::new("exp", V::exp));
+        registry.register(UnaryFunction::new("log", V::log));
+        registry.register(UnaryFunction::new("sqrt", sqrt));
+        registry.register(BinaryFunction::new("compose", V::compose));
+        registry.register(UnaryFunction::new("revert", V::revert));
+        registry.register(UnaryFunction::new("D", V::derivative));
+        registry.register(Integral);
+        registry.register(UnaryFunction::new("factor", factor));
+        registry
+    }
+}
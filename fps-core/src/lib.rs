@@ -1,4 +1,16 @@
+pub mod coefficient;
+mod dense;
+pub mod diagnostic;
 pub mod evaluator;
+pub mod factor;
+pub mod functions;
+pub mod modint;
+mod multimodular;
+pub mod multivariate;
+mod ntt;
 pub mod parser;
+pub mod polynomial;
+pub mod query;
+pub mod rational_function;
 pub mod series;
 pub mod tokenizer;
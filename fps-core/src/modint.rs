@@ -0,0 +1,454 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive};
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::coefficient::{Coefficient, Field};
+use crate::evaluator::EvalError;
+use crate::multimodular;
+use crate::ntt;
+
+/// Largest supported modulus; residues below it multiply without overflow.
+pub const MAX_MODULUS: u64 = 1 << 32;
+
+/// An integer modulo a prime, stored as its residue in `0..modulus`.
+///
+/// Both operands of an arithmetic operation must share the modulus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ModInt {
+    value: u64,
+    modulus: u64,
+}
+
+impl ModInt {
+    pub fn new(value: u64, modulus: u64) -> Self {
+        Self {
+            value: value % modulus,
+            modulus,
+        }
+    }
+
+    pub fn zero(modulus: u64) -> Self {
+        Self::new(0, modulus)
+    }
+
+    pub fn one(modulus: u64) -> Self {
+        Self::new(1, modulus)
+    }
+
+    pub fn from_integer(value: &BigInt, modulus: u64) -> Self {
+        let residue = value % BigInt::from(modulus);
+        let residue = if residue.is_negative() {
+            residue + modulus
+        } else {
+            residue
+        };
+        Self::new(residue.to_u64().expect("residue below modulus"), modulus)
+    }
+
+    /// `numer / denom` modulo `modulus`, failing if the denominator is
+    /// divisible by it.
+    pub fn from_rational(value: &BigRational, modulus: u64) -> Result<Self, EvalError> {
+        let numer = Self::from_integer(value.numer(), modulus);
+        let denom = Self::from_integer(value.denom(), modulus);
+        if denom.is_zero() {
+            return Err(EvalError::NotInvertible(value.denom().to_string(), modulus));
+        }
+        Ok(numer * denom.inverse()?)
+    }
+
+    /// Residue in `0..modulus`.
+    pub fn value(self) -> u64 {
+        self.value
+    }
+
+    pub fn modulus(self) -> u64 {
+        self.modulus
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.value == 0
+    }
+
+    pub fn pow(self, mut exponent: u64) -> Self {
+        let mut result = Self::one(self.modulus);
+        let mut base = self;
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent /= 2;
+        }
+        result
+    }
+
+    /// Multiplicative inverse, by Fermat's little theorem.
+    pub fn inverse(self) -> Result<Self, EvalError> {
+        if self.is_zero() {
+            return Err(EvalError::NotInvertible(
+                self.value.to_string(),
+                self.modulus,
+            ));
+        }
+        Ok(self.pow(self.modulus - 2))
+    }
+
+    /// A `d`-th root, if one exists: the unique one when `d` is coprime to
+    /// `modulus - 1`, or the smaller of the two square roots for `d = 2`.
+    pub fn root(self, d: u64) -> Option<Self> {
+        let p = self.modulus;
+        if self.is_zero() || self.value == 1 {
+            return Some(self);
+        }
+        if gcd(d, p - 1) == 1 {
+            // d e = 1 mod (p - 1), so (a^e)^d = a.
+            let e = inverse_mod(d % (p - 1), p - 1);
+            return Some(self.pow(e));
+        }
+        if d != 2 {
+            return None;
+        }
+
+        let root = self.sqrt()?;
+        Some(if root.value <= p - root.value {
+            root
+        } else {
+            -root
+        })
+    }
+
+    /// A square root by the Tonelli-Shanks algorithm.
+    fn sqrt(self) -> Option<Self> {
+        let p = self.modulus;
+        if p == 2 {
+            return Some(self);
+        }
+        if self.pow((p - 1) / 2).value != 1 {
+            return None;
+        }
+
+        // p - 1 = q 2^s with q odd, and z a quadratic non-residue.
+        let s = (p - 1).trailing_zeros();
+        let q = (p - 1) >> s;
+        let z = (2..p)
+            .map(|z| Self::new(z, p))
+            .find(|z| z.pow((p - 1) / 2).value == p - 1)
+            .expect("a prime has a non-residue");
+
+        let mut m = s;
+        let mut c = z.pow(q);
+        let mut t = self.pow(q);
+        let mut r = self.pow(q.div_ceil(2));
+        while t.value != 1 {
+            let mut i = 0;
+            let mut t2 = t;
+            while t2.value != 1 {
+                t2 = t2 * t2;
+                i += 1;
+            }
+            let b = c.pow(1 << (m - i - 1));
+            m = i;
+            c = b * b;
+            t = t * c;
+            r = r * b;
+        }
+        Some(r)
+    }
+}
+
+impl Add for ModInt {
+    type Output = ModInt;
+
+    fn add(self, other: ModInt) -> ModInt {
+        debug_assert_eq!(self.modulus, other.modulus);
+        let sum = self.value + other.value;
+        ModInt {
+            value: if sum >= self.modulus {
+                sum - self.modulus
+            } else {
+                sum
+            },
+            modulus: self.modulus,
+        }
+    }
+}
+
+impl Sub for ModInt {
+    type Output = ModInt;
+
+    fn sub(self, other: ModInt) -> ModInt {
+        self + -other
+    }
+}
+
+impl Mul for ModInt {
+    type Output = ModInt;
+
+    fn mul(self, other: ModInt) -> ModInt {
+        debug_assert_eq!(self.modulus, other.modulus);
+        ModInt::new(self.value * other.value, self.modulus)
+    }
+}
+
+impl Neg for ModInt {
+    type Output = ModInt;
+
+    fn neg(self) -> ModInt {
+        ModInt::new(self.modulus - self.value, self.modulus)
+    }
+}
+
+impl fmt::Display for ModInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+/// Residues modulo the prime given by the context.
+impl Coefficient for ModInt {
+    type Context = u64;
+
+    fn from_integer(value: &BigInt, modulus: &u64) -> Self {
+        ModInt::from_integer(value, *modulus)
+    }
+
+    fn is_zero(&self) -> bool {
+        ModInt::is_zero(*self)
+    }
+
+    fn is_one(&self) -> bool {
+        self.value == 1
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        *self + *other
+    }
+
+    fn neg(&self) -> Self {
+        -*self
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        *self * *other
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        *self - *other
+    }
+
+    /// None: a residue stands for every rational congruent to it, so using
+    /// it as, say, an exponent would give a wrong result.
+    fn to_rational(&self) -> Option<BigRational> {
+        None
+    }
+
+    /// Multiplication by the number-theoretic transform.
+    fn mul_truncated(a: &[Self], b: &[Self], len: usize, modulus: &u64) -> Vec<Self> {
+        let a = &a[..a.len().min(len)];
+        let b = &b[..b.len().min(len)];
+        let mut result = vec![ModInt::zero(*modulus); len];
+        if a.is_empty() || b.is_empty() {
+            return result;
+        }
+
+        let values = |v: &[ModInt]| v.iter().map(|c| c.value).collect::<Vec<_>>();
+        let product = ntt::convolve(&values(a), &values(b), *modulus);
+        for (r, c) in result.iter_mut().zip(product) {
+            *r = ModInt::new(c, *modulus);
+        }
+        result
+    }
+}
+
+impl Field for ModInt {
+    fn from_rational(value: &BigRational, modulus: &u64) -> Result<Self, EvalError> {
+        ModInt::from_rational(value, *modulus)
+    }
+
+    fn inverse(&self) -> Result<Self, EvalError> {
+        ModInt::inverse(*self)
+    }
+
+    fn reciprocal(n: i64, modulus: &u64) -> Result<Self, EvalError> {
+        ModInt::new(n.rem_euclid(*modulus as i64) as u64, *modulus)
+            .inverse()
+            .map_err(|_| EvalError::NotInvertible(n.to_string(), *modulus))
+    }
+
+    /// `self^exponent` from a root of `self`. Of two square roots, this
+    /// takes the image of a small positive fraction if there is one, like
+    /// the rational square root, and otherwise the smaller one.
+    fn powr(&self, exponent: &BigRational) -> Result<Self, EvalError> {
+        let no_power =
+            || EvalError::NoModularPower(self.to_string(), exponent.to_string(), self.modulus);
+        let (Some(numer), Some(denom)) = (exponent.numer().to_i64(), exponent.denom().to_u64())
+        else {
+            return Err(EvalError::ExponentTooLarge);
+        };
+        let mut root = self.root(denom).ok_or_else(no_power)?;
+        if denom == 2
+            && !multimodular::is_small_positive(root)
+            && multimodular::is_small_positive(-root)
+        {
+            root = -root;
+        }
+        let power = root.pow(numer.unsigned_abs());
+        if numer < 0 {
+            power.inverse()
+        } else {
+            Ok(power)
+        }
+    }
+}
+
+/// Checks that `modulus` is a prime below [`MAX_MODULUS`].
+pub fn check_modulus(modulus: u64) -> Result<(), EvalError> {
+    if modulus >= MAX_MODULUS || !is_prime(modulus) {
+        return Err(EvalError::InvalidModulus(modulus));
+    }
+    Ok(())
+}
+
+/// Miller-Rabin test with the bases 2, 7 and 61, which is exact for numbers
+/// below 2^32.
+pub(crate) fn is_prime(n: u64) -> bool {
+    debug_assert!(n < MAX_MODULUS);
+    if n < 2 {
+        return false;
+    }
+    if let Some(&p) = [2, 3, 5, 7, 61].iter().find(|&&p| n.is_multiple_of(p)) {
+        return n == p;
+    }
+
+    // n - 1 = d 2^s with d odd.
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    [2, 7, 61].iter().all(|&a| {
+        let mut x = ModInt::new(a, n).pow(d);
+        if x.value == 1 || x.value == n - 1 {
+            return true;
+        }
+        (1..s).any(|_| {
+            x = x * x;
+            x.value == n - 1
+        })
+    })
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Inverse of `a` modulo `m`, for coprime `a` and `m`.
+pub(crate) fn inverse_mod(a: u64, m: u64) -> u64 {
+    let (mut old_r, mut r) = (a as i128, m as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+    }
+    old_s.rem_euclid(m as i128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::series::Series;
+
+    const P: u64 = 998244353;
+
+    #[test]
+    fn reduces_rationals() {
+        let half = ModInt::from_rational(&BigRational::new(1.into(), 2.into()), P).unwrap();
+        assert_eq!(half.value(), P.div_ceil(2));
+        assert_eq!(half * ModInt::new(2, P), ModInt::one(P));
+
+        let minus_one = ModInt::from_integer(&BigInt::from(-1), P);
+        assert_eq!(minus_one.value(), P - 1);
+
+        let third = BigRational::new(1.into(), 3.into());
+        assert_eq!(
+            ModInt::from_rational(&third, 3),
+            Err(EvalError::NotInvertible("3".to_string(), 3))
+        );
+    }
+
+    #[test]
+    fn takes_roots() {
+        assert_eq!(ModInt::new(4, P).root(2), Some(ModInt::new(2, P)));
+        assert_eq!(ModInt::new(3, P).root(2), None);
+        let cube = ModInt::new(5, 1000000007).pow(3);
+        assert_eq!(cube.root(3).map(|r| r.pow(3)), Some(cube));
+    }
+
+    #[test]
+    fn accepts_only_primes() {
+        assert!(check_modulus(P).is_ok());
+        assert!(check_modulus(2).is_ok());
+        assert_eq!(check_modulus(1), Err(EvalError::InvalidModulus(1)));
+        assert_eq!(check_modulus(91), Err(EvalError::InvalidModulus(91)));
+        assert_eq!(
+            check_modulus(MAX_MODULUS + 15),
+            Err(EvalError::InvalidModulus(MAX_MODULUS + 15))
+        );
+
+        let trial_division = |n: u64| {
+            n >= 2
+                && (2..n)
+                    .take_while(|d| d * d <= n)
+                    .all(|d| !n.is_multiple_of(d))
+        };
+        assert!((0..20000).all(|n| is_prime(n) == trial_division(n)));
+        // A strong pseudoprime to the bases 2, 3, 5 and 7.
+        assert!(!is_prime(3215031751));
+        assert!(is_prime(4294967291));
+    }
+
+    fn reduce(series: &Series) -> Vec<ModInt> {
+        series
+            .coeffs()
+            .iter()
+            .map(|c| ModInt::from_rational(c, P).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn series_functions_agree_with_rational_ones() {
+        let two_thirds = BigRational::new(2.into(), 3.into());
+        let half = BigRational::new(1.into(), 2.into());
+        let x = Series::variable(60);
+        let g = x.add(&x.powi(3).unwrap().scale(&two_thirds));
+        let f = Series::one(60).add(&g);
+
+        let x = Series::<ModInt>::variable_in(60, &P);
+        let two_thirds = ModInt::from_rational(&two_thirds, P).unwrap();
+        let m = x.add(&x.powi(3).unwrap().scale(&two_thirds));
+        let n = Series::one_in(60, &P).add(&m);
+        assert_eq!(m.exp().unwrap().coeffs(), reduce(&g.exp().unwrap()));
+        assert_eq!(m.sin().unwrap().coeffs(), reduce(&g.sin().unwrap()));
+        assert_eq!(m.cos().unwrap().coeffs(), reduce(&g.cos().unwrap()));
+        assert_eq!(n.log().unwrap().coeffs(), reduce(&f.log().unwrap()));
+        assert_eq!(n.inverse().unwrap().coeffs(), reduce(&f.inverse().unwrap()));
+        assert_eq!(
+            n.powr(&half).unwrap().coeffs(),
+            reduce(&f.powr(&half).unwrap())
+        );
+        assert_eq!(
+            m.reversion().unwrap().coeffs(),
+            reduce(&g.reversion().unwrap())
+        );
+    }
+
+    #[test]
+    fn series_report_missing_inverses() {
+        let x = Series::<ModInt>::variable_in(8, &7);
+        assert_eq!(x.exp(), Err(EvalError::NotInvertible("7".to_string(), 7)));
+        assert_eq!(
+            format!("{}", x.scale(&ModInt::new(6, 7)).mul(&x)),
+            "6 x^2 + O(x^10)"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Exact rationals from their residues modulo several word-size primes.
+//!
+//! The residues of a coefficient modulo each prime are combined by the
+//! Chinese remainder theorem into one residue modulo their product, from
+//! which rational reconstruction recovers the fraction `n/d` once the
+//! product exceeds `2 |n| d`. Before that, reconstruction either fails or
+//! gives a wrong fraction, which the residues modulo the next prime expose.
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+use crate::factor::gcd;
+use crate::modint::{self, MAX_MODULUS, ModInt};
+
+/// Primes below [`MAX_MODULUS`], largest first. Those of the form
+/// `c 2^k + 1`, with `2^k` at least `ntt_size`, come first, as their products
+/// by the number-theoretic transform need no further primes.
+pub(crate) fn primes(ntt_size: usize) -> impl Iterator<Item = u64> {
+    let step = ntt_size.next_power_of_two() as u64;
+    let transform_friendly = (1..MAX_MODULUS / step)
+        .rev()
+        .map(move |c| c * step + 1)
+        .filter(|&p| modint::is_prime(p));
+    let others = (3..MAX_MODULUS)
+        .rev()
+        .step_by(2)
+        .filter(move |&p| (p - 1) % step != 0 && modint::is_prime(p));
+    transform_friendly.chain(others)
+}
+
+/// Bits by which [`Combined::reconstruct`] keeps numerators and denominators
+/// below `sqrt(m/2)`. A residue that is not that of such a small fraction
+/// then passes for one only with a probability of about `2^-20`, rather than
+/// more often than not.
+const SLACK_BITS: u32 = 10;
+
+/// Residues of a sequence of integers modulo a growing product of primes.
+pub(crate) struct Combined {
+    residues: Vec<BigInt>,
+    modulus: BigInt,
+    /// Fractions found for the first residues by earlier reconstructions,
+    /// and a common denominator of them.
+    values: Vec<BigRational>,
+    denominator: BigInt,
+}
+
+impl Combined {
+    pub(crate) fn new(residues: &[ModInt], modulus: u64) -> Self {
+        Combined {
+            residues: residues.iter().map(|r| BigInt::from(r.value())).collect(),
+            modulus: BigInt::from(modulus),
+            values: Vec::new(),
+            denominator: BigInt::one(),
+        }
+    }
+
+    /// Size of the product of the primes in bits.
+    pub(crate) fn bits(&self) -> u64 {
+        self.modulus.bits()
+    }
+
+    /// Adds the residues modulo a further prime `p`: with `x = a mod m`, the
+    /// combined residue is `a + m ((b - a) / m mod p)` modulo `m p`.
+    pub(crate) fn add(&mut self, residues: &[ModInt], p: u64) {
+        let m_inverse = ModInt::from_integer(&self.modulus, p)
+            .inverse()
+            .expect("distinct primes are coprime");
+        for (a, &b) in self.residues.iter_mut().zip(residues) {
+            let t = (b - ModInt::from_integer(a, p)) * m_inverse;
+            *a += &self.modulus * t.value();
+        }
+        self.modulus *= p;
+    }
+
+    /// The fractions with these residues, if all of them are small enough.
+    ///
+    /// Each residue is first multiplied by a common denominator of the
+    /// fractions before it, so only the part of its denominator they do not
+    /// share needs to be small, as for the coefficients `1/n!` of `exp(x)`;
+    /// where that leaves too large a numerator, as for the `1/n` of
+    /// `log(1+x)`, the residue is reconstructed on its own. Fractions found
+    /// before are kept as long as the residues still agree with them.
+    pub(crate) fn reconstruct(&mut self) -> Option<Vec<BigRational>> {
+        let agrees = |value: &BigRational, a: &BigInt| {
+            ((value.numer() - value.denom() * a) % &self.modulus).is_zero()
+        };
+        if !self
+            .values
+            .iter()
+            .zip(&self.residues)
+            .all(|(v, a)| agrees(v, a))
+        {
+            self.values.clear();
+            self.denominator = BigInt::one();
+        }
+
+        let bound = (&self.modulus >> 1u32).sqrt() >> SLACK_BITS;
+        let reconstruct = |a: &BigInt| reconstruct_within(a, &self.modulus, &bound);
+        let mut denominator_residue = &self.denominator % &self.modulus;
+        for a in &self.residues[self.values.len()..] {
+            let scaled = a * &denominator_residue % &self.modulus;
+            let value = match reconstruct(&scaled) {
+                Some(scaled) if !scaled.is_zero() => {
+                    let (numer, denom) = (scaled.numer(), scaled.denom());
+                    // The numerator is coprime to `denom` and usually small,
+                    // so reducing by it is cheap.
+                    let common = gcd(numer, &(&self.denominator % numer));
+                    let value =
+                        BigRational::new_raw(numer / &common, denom * &self.denominator / &common);
+                    self.denominator *= denom;
+                    denominator_residue = denominator_residue * denom % &self.modulus;
+                    value
+                }
+                Some(zero) => zero,
+                None => reconstruct(a)?,
+            };
+            self.values.push(value);
+        }
+        Some(self.values.clone())
+    }
+}
+
+/// Whether `values` reduce to `residues` modulo their prime.
+pub(crate) fn reduce_to(values: &[BigRational], residues: &[ModInt], p: u64) -> bool {
+    values.len() == residues.len()
+        && values
+            .iter()
+            .zip(residues)
+            .all(|(value, &r)| ModInt::from_rational(value, p) == Ok(r))
+}
+
+/// The fraction `n/d` with `n = a d mod m` and `|n|, d <= sqrt(m/2)`, which
+/// is unique if it exists.
+pub(crate) fn reconstruct(a: &BigInt, m: &BigInt) -> Option<BigRational> {
+    reconstruct_within(a, m, &(m >> 1u32).sqrt())
+}
+
+/// The fraction `n/d` with `n = a d mod m` and `|n|, d <= bound`, by the
+/// extended Euclidean algorithm on `m` and `a`. It is unique if `bound` is
+/// at most `sqrt(m/2)`.
+fn reconstruct_within(a: &BigInt, m: &BigInt, bound: &BigInt) -> Option<BigRational> {
+    let (mut r0, mut r1) = (m.clone(), a % m);
+    let (mut t0, mut t1) = (BigInt::zero(), BigInt::one());
+    while &r1 > bound {
+        let q = &r0 / &r1;
+        let r2 = &r0 - &q * &r1;
+        r0 = std::mem::replace(&mut r1, r2);
+        let t2 = &t0 - &q * &t1;
+        t0 = std::mem::replace(&mut t1, t2);
+    }
+
+    if &t1.abs() > bound || !gcd(&r1, &t1).is_one() {
+        return None;
+    }
+    Some(BigRational::new(r1, t1))
+}
+
+/// Whether the residue `a` is the image of a fraction with a positive
+/// numerator and a numerator and denominator below `sqrt(modulus/2)`.
+pub(crate) fn is_small_positive(a: ModInt) -> bool {
+    let value = reconstruct(&BigInt::from(a.value()), &BigInt::from(a.modulus()));
+    value.is_some_and(|v| v.is_positive())
+}
+
+/// Size in bits of the product of primes beyond which reconstruction gives
+/// up and the exact computation takes over: two primes, and 16 bits per
+/// coefficient. Past that, evaluating modulo that many primes usually costs
+/// more than computing with the exact coefficients.
+pub(crate) fn max_bits(max_degree: usize) -> u64 {
+    64 + 16 * (max_degree as u64 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconstructs_fractions_from_residues() {
+        let primes: Vec<u64> = primes(16).take(4).collect();
+        let values = [
+            BigRational::new((-123456789).into(), 1000.into()),
+            BigRational::new(1.into(), 3628800.into()),
+            BigRational::new((-1).into(), 39916800.into()),
+            BigRational::zero(),
+        ];
+        let residues = |p: u64| {
+            values
+                .iter()
+                .map(|v| ModInt::from_rational(v, p).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let mut combined = Combined::new(&residues(primes[0]), primes[0]);
+        combined.add(&residues(primes[1]), primes[1]);
+        assert_eq!(combined.reconstruct(), None);
+        combined.add(&residues(primes[2]), primes[2]);
+        assert_eq!(combined.reconstruct().as_deref(), Some(&values[..]));
+        assert!(reduce_to(&values, &residues(primes[3]), primes[3]));
+    }
+
+    #[test]
+    fn prefers_transform_friendly_primes() {
+        let primes: Vec<u64> = primes(1 << 20).take(2).collect();
+        assert_eq!(primes, [4293918721, 4276092929]);
+    }
+}
@@ -0,0 +1,508 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::evaluator::EvalError;
+use crate::series::{format_rational, rational_power};
+
+/// Which monomials a [`MultiSeries`] keeps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Truncation {
+    /// Keep monomials whose total degree is at most the given bound.
+    TotalDegree(usize),
+    /// Keep monomials whose exponent in each variable is at most the
+    /// corresponding bound.
+    PerVariable(Vec<usize>),
+}
+
+impl Truncation {
+    fn contains(&self, exponents: &[usize]) -> bool {
+        match self {
+            Truncation::TotalDegree(max) => exponents.iter().sum::<usize>() <= *max,
+            Truncation::PerVariable(bounds) => {
+                exponents.iter().zip(bounds).all(|(e, bound)| e <= bound)
+            }
+        }
+    }
+
+    /// Truncation keeping only monomials known under both `self` and
+    /// `other`. Mixed kinds fall back to the largest total degree bound that
+    /// lies within both.
+    fn meet(&self, other: &Truncation) -> Truncation {
+        match (self, other) {
+            (Truncation::TotalDegree(a), Truncation::TotalDegree(b)) => {
+                Truncation::TotalDegree(*a.min(b))
+            }
+            (Truncation::PerVariable(a), Truncation::PerVariable(b)) => {
+                Truncation::PerVariable(a.iter().zip(b).map(|(a, b)| *a.min(b)).collect())
+            }
+            (Truncation::TotalDegree(max), Truncation::PerVariable(bounds))
+            | (Truncation::PerVariable(bounds), Truncation::TotalDegree(max)) => {
+                let min_bound = bounds.iter().copied().min().unwrap_or(*max);
+                Truncation::TotalDegree((*max).min(min_bound))
+            }
+        }
+    }
+
+    /// Largest total degree of a monomial that survives truncation.
+    fn max_total_degree(&self) -> usize {
+        match self {
+            Truncation::TotalDegree(max) => *max,
+            Truncation::PerVariable(bounds) => bounds.iter().sum(),
+        }
+    }
+}
+
+/// Truncated power series in several variables.
+///
+/// Coefficients are stored sparsely, keyed by the exponent of each variable
+/// in the order given by [`MultiSeries::variables`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiSeries {
+    variables: Vec<char>,
+    truncation: Truncation,
+    terms: BTreeMap<Vec<usize>, BigRational>,
+}
+
+impl MultiSeries {
+    pub fn zero(variables: Vec<char>, truncation: Truncation) -> Self {
+        if let Truncation::PerVariable(bounds) = &truncation {
+            assert_eq!(bounds.len(), variables.len());
+        }
+        Self {
+            variables,
+            truncation,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn one(variables: Vec<char>, truncation: Truncation) -> Self {
+        Self::constant(BigRational::one(), variables, truncation)
+    }
+
+    pub fn constant(value: BigRational, variables: Vec<char>, truncation: Truncation) -> Self {
+        let mut series = Self::zero(variables, truncation);
+        let origin = vec![0; series.variables.len()];
+        series.set(origin, value);
+        series
+    }
+
+    /// The series consisting of the variable at `index` alone.
+    pub fn variable(index: usize, variables: Vec<char>, truncation: Truncation) -> Self {
+        let mut series = Self::zero(variables, truncation);
+        let mut exponents = vec![0; series.variables.len()];
+        exponents[index] = 1;
+        series.set(exponents, BigRational::one());
+        series
+    }
+
+    pub fn variables(&self) -> &[char] {
+        &self.variables
+    }
+
+    pub fn truncation(&self) -> &Truncation {
+        &self.truncation
+    }
+
+    /// Coefficient of the monomial with the given exponents.
+    pub fn coefficient(&self, exponents: &[usize]) -> BigRational {
+        self.terms
+            .get(exponents)
+            .cloned()
+            .unwrap_or_else(BigRational::zero)
+    }
+
+    /// Non-zero terms, keyed by exponent vector.
+    pub fn terms(&self) -> impl Iterator<Item = (&[usize], &BigRational)> {
+        self.terms.iter().map(|(e, c)| (e.as_slice(), c))
+    }
+
+    pub fn constant_term(&self) -> BigRational {
+        self.coefficient(&vec![0; self.variables.len()])
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.terms.keys().all(|e| e.iter().all(|&k| k == 0))
+    }
+
+    fn set(&mut self, exponents: Vec<usize>, value: BigRational) {
+        if value.is_zero() || !self.truncation.contains(&exponents) {
+            self.terms.remove(&exponents);
+        } else {
+            self.terms.insert(exponents, value);
+        }
+    }
+
+    fn accumulate(&mut self, exponents: Vec<usize>, value: BigRational) {
+        if !self.truncation.contains(&exponents) {
+            return;
+        }
+        let sum = self.coefficient(&exponents) + value;
+        self.set(exponents, sum);
+    }
+
+    fn zero_like(&self) -> MultiSeries {
+        MultiSeries::zero(self.variables.clone(), self.truncation.clone())
+    }
+
+    fn one_like(&self) -> MultiSeries {
+        MultiSeries::one(self.variables.clone(), self.truncation.clone())
+    }
+
+    /// Empty series over the same variables, known only where both `self`
+    /// and `other` are.
+    fn zero_meet(&self, other: &MultiSeries) -> MultiSeries {
+        assert_eq!(self.variables, other.variables);
+        MultiSeries::zero(
+            self.variables.clone(),
+            self.truncation.meet(&other.truncation),
+        )
+    }
+
+    /// Sum of both series, truncated to the precision they share.
+    pub fn add(&self, other: &MultiSeries) -> MultiSeries {
+        let mut result = self.zero_meet(other);
+        for (exponents, coeff) in self.terms.iter().chain(&other.terms) {
+            result.accumulate(exponents.clone(), coeff.clone());
+        }
+        result
+    }
+
+    pub fn neg(&self) -> MultiSeries {
+        let mut result = self.clone();
+        for coeff in result.terms.values_mut() {
+            *coeff = -coeff.clone();
+        }
+        result
+    }
+
+    pub fn sub(&self, other: &MultiSeries) -> MultiSeries {
+        self.add(&other.neg())
+    }
+
+    /// Product of both series, truncated to the precision they share.
+    pub fn mul(&self, other: &MultiSeries) -> MultiSeries {
+        let mut result = self.zero_meet(other);
+
+        for (a_exp, a) in &self.terms {
+            for (b_exp, b) in &other.terms {
+                let exponents: Vec<usize> = a_exp.iter().zip(b_exp).map(|(i, j)| i + j).collect();
+                result.accumulate(exponents, a.clone() * b.clone());
+            }
+        }
+
+        result
+    }
+
+    pub fn scale(&self, scalar: &BigRational) -> MultiSeries {
+        let mut result = self.zero_like();
+        for (exponents, coeff) in &self.terms {
+            result.set(exponents.clone(), coeff.clone() * scalar.clone());
+        }
+        result
+    }
+
+    /// Sums `coeff(k) * self^k` for `k` from `start` until the powers vanish
+    /// under truncation. Requires a zero constant term.
+    fn power_sum(&self, start: usize, coeff: impl Fn(usize) -> Option<BigRational>) -> MultiSeries {
+        let mut result = self.zero_like();
+        let mut power = self.one_like();
+        for k in 0..=self.truncation.max_total_degree() {
+            if k >= start
+                && let Some(c) = coeff(k)
+            {
+                result = result.add(&power.scale(&c));
+            }
+            power = power.mul(self);
+            if power.terms.is_empty() {
+                break;
+            }
+        }
+        result
+    }
+
+    pub fn inverse(&self) -> Result<MultiSeries, EvalError> {
+        let a0 = self.constant_term();
+        if a0.is_zero() {
+            return Err(EvalError::DivisionByZero);
+        }
+
+        // 1 / (a0 + g) = 1/a0 * sum (-g/a0)^k
+        let inv_a0 = BigRational::one() / a0.clone();
+        let ratio = self
+            .sub(&self.one_like().scale(&a0))
+            .scale(&-inv_a0.clone());
+        Ok(ratio.power_sum(0, |_| Some(inv_a0.clone())))
+    }
+
+    pub fn div(&self, other: &MultiSeries) -> Result<MultiSeries, EvalError> {
+        let inverse = other.inverse()?;
+        Ok(self.mul(&inverse))
+    }
+
+    pub fn powi(&self, exponent: i64) -> Result<MultiSeries, EvalError> {
+        if exponent < 0 {
+            return self.inverse()?.powi(-exponent);
+        }
+
+        let mut result = self.one_like();
+        let mut base = self.clone();
+        let mut exp = exponent;
+
+        while exp > 0 {
+            if exp % 2 == 1 {
+                result = result.mul(&base);
+            }
+            exp /= 2;
+            if exp > 0 {
+                base = base.mul(&base);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Power with a rational exponent via the generalized binomial series.
+    pub fn powr(&self, exponent: &BigRational) -> Result<MultiSeries, EvalError> {
+        let a0 = self.constant_term();
+        if a0.is_zero() {
+            return Err(EvalError::NonIntegerPowerOfZero);
+        }
+        let leading = rational_power(&a0, exponent)?;
+
+        // (a0 (1 + g))^e = a0^e sum binom(e, k) g^k
+        let adjustment = self.scale(&(BigRational::one() / a0)).sub(&self.one_like());
+        let series = adjustment.power_sum(0, |k| {
+            let mut binomial = BigRational::one();
+            for i in 0..k {
+                binomial = binomial * (exponent - BigInt::from(i)) / BigInt::from(i + 1);
+            }
+            Some(binomial)
+        });
+        Ok(series.scale(&leading))
+    }
+
+    pub fn sin(&self) -> Result<MultiSeries, EvalError> {
+        if !self.constant_term().is_zero() {
+            return Err(EvalError::FunctionRequiresZeroConstant("sin"));
+        }
+        Ok(self.power_sum(0, |k| {
+            (k % 2 == 1).then(|| signed_inverse_factorial(k, (k / 2) % 2 == 1))
+        }))
+    }
+
+    pub fn cos(&self) -> Result<MultiSeries, EvalError> {
+        if !self.constant_term().is_zero() {
+            return Err(EvalError::FunctionRequiresZeroConstant("cos"));
+        }
+        Ok(self.power_sum(0, |k| {
+            (k % 2 == 0).then(|| signed_inverse_factorial(k, (k / 2) % 2 == 1))
+        }))
+    }
+
+    pub fn exp(&self) -> Result<MultiSeries, EvalError> {
+        if !self.constant_term().is_zero() {
+            return Err(EvalError::FunctionRequiresZeroConstant("exp"));
+        }
+        Ok(self.power_sum(0, |k| Some(signed_inverse_factorial(k, false))))
+    }
+
+    pub fn log(&self) -> Result<MultiSeries, EvalError> {
+        if self.constant_term() != BigRational::one() {
+            return Err(EvalError::LogRequiresUnitConstant);
+        }
+
+        // log(1 + g) = sum (-1)^(k+1) g^k / k
+        let adjustment = self.sub(&self.one_like());
+        Ok(adjustment.power_sum(1, |k| {
+            let sign = if k % 2 == 1 { 1 } else { -1 };
+            Some(BigRational::new(BigInt::from(sign), BigInt::from(k)))
+        }))
+    }
+}
+
+fn signed_inverse_factorial(k: usize, negative: bool) -> BigRational {
+    let mut factorial = BigInt::one();
+    for i in 2..=k {
+        factorial *= BigInt::from(i);
+    }
+    let sign = if negative {
+        -BigInt::one()
+    } else {
+        BigInt::one()
+    };
+    BigRational::new(sign, factorial)
+}
+
+impl fmt::Display for MultiSeries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut terms: Vec<_> = self.terms.iter().collect();
+        terms.sort_by_key(|(exponents, _)| {
+            (exponents.iter().sum::<usize>(), Reverse(exponents.to_vec()))
+        });
+
+        let mut first = true;
+        for (exponents, coeff) in terms {
+            let is_negative = coeff.is_negative();
+            let abs_coeff = coeff.abs();
+            let term = self.format_term(&abs_coeff, exponents);
+
+            if first {
+                if is_negative {
+                    write!(f, "-{}", term)?;
+                } else {
+                    write!(f, "{}", term)?;
+                }
+                first = false;
+            } else if is_negative {
+                write!(f, " - {}", term)?;
+            } else {
+                write!(f, " + {}", term)?;
+            }
+        }
+
+        if first {
+            write!(f, "0")?;
+        }
+
+        match &self.truncation {
+            Truncation::TotalDegree(max) => {
+                let names: Vec<String> = self.variables.iter().map(|v| v.to_string()).collect();
+                write!(f, " + O(({})^{})", names.join(", "), max + 1)
+            }
+            Truncation::PerVariable(bounds) => {
+                let parts: Vec<String> = self
+                    .variables
+                    .iter()
+                    .zip(bounds)
+                    .map(|(v, bound)| format!("{}^{}", v, bound + 1))
+                    .collect();
+                write!(f, " + O({})", parts.join(", "))
+            }
+        }
+    }
+}
+
+impl MultiSeries {
+    fn format_term(&self, coeff: &BigRational, exponents: &[usize]) -> String {
+        let monomial: Vec<String> = self
+            .variables
+            .iter()
+            .zip(exponents)
+            .filter(|(_, e)| **e > 0)
+            .map(|(v, e)| {
+                if *e == 1 {
+                    v.to_string()
+                } else {
+                    format!("{}^{}", v, e)
+                }
+            })
+            .collect();
+
+        if monomial.is_empty() {
+            format_rational(coeff)
+        } else if coeff.is_one() {
+            monomial.join(" ")
+        } else {
+            format!("{} {}", format_rational(coeff), monomial.join(" "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xy(truncation: Truncation) -> (MultiSeries, MultiSeries) {
+        let vars = vec!['x', 'y'];
+        (
+            MultiSeries::variable(0, vars.clone(), truncation.clone()),
+            MultiSeries::variable(1, vars, truncation),
+        )
+    }
+
+    fn rational(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(numer.into(), denom.into())
+    }
+
+    #[test]
+    fn total_degree_truncation() {
+        let (x, y) = xy(Truncation::TotalDegree(2));
+        let sum = x.add(&y);
+        let square = sum.powi(2).unwrap();
+        assert_eq!(format!("{}", square), "x^2 + 2 x y + y^2 + O((x, y)^3)");
+        assert!(sum.powi(3).unwrap().terms().next().is_none());
+    }
+
+    #[test]
+    fn per_variable_truncation() {
+        let (x, y) = xy(Truncation::PerVariable(vec![2, 1]));
+        let one = MultiSeries::one(vec!['x', 'y'], Truncation::PerVariable(vec![2, 1]));
+        let inverse = one.sub(&x).sub(&y).inverse().unwrap();
+
+        // 1/(1 - x - y) = sum over (i, j) of binomial(i + j, i) x^i y^j
+        assert_eq!(inverse.coefficient(&[2, 1]), rational(3, 1));
+        assert_eq!(inverse.coefficient(&[1, 1]), rational(2, 1));
+        assert_eq!(inverse.coefficient(&[0, 2]), rational(0, 1));
+        assert_eq!(
+            format!("{}", inverse),
+            "1 + x + y + x^2 + 2 x y + 3 x^2 y + O(x^3, y^2)"
+        );
+    }
+
+    #[test]
+    fn rational_power_round_trip() {
+        let (x, y) = xy(Truncation::TotalDegree(4));
+        let one = MultiSeries::one(vec!['x', 'y'], Truncation::TotalDegree(4));
+        let g = one.scale(&rational(9, 4)).add(&x).sub(&x.mul(&y));
+        let cube_root = g.powr(&rational(1, 3));
+        assert!(matches!(cube_root, Err(EvalError::IrrationalPower(_, _))));
+        let root = g.powr(&rational(3, 2)).unwrap();
+        assert_eq!(root.powi(2).unwrap(), g.powi(3).unwrap());
+    }
+
+    #[test]
+    fn exp_log_round_trip() {
+        let (x, y) = xy(Truncation::TotalDegree(4));
+        let g = x.mul(&y).add(&x.scale(&rational(1, 2)));
+        let round_trip = g.exp().unwrap().log().unwrap();
+        assert_eq!(round_trip, g);
+    }
+
+    #[test]
+    fn sin_squared_plus_cos_squared() {
+        let (x, y) = xy(Truncation::TotalDegree(5));
+        let g = x.sub(&y.scale(&rational(3, 1)));
+        let sin = g.sin().unwrap();
+        let cos = g.cos().unwrap();
+        let one = MultiSeries::one(vec!['x', 'y'], Truncation::TotalDegree(5));
+        assert_eq!(sin.mul(&sin).add(&cos.mul(&cos)), one);
+    }
+
+    #[test]
+    fn inverse_requires_non_zero_constant_term() {
+        let (x, _) = xy(Truncation::TotalDegree(3));
+        assert_eq!(x.inverse(), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn arithmetic_keeps_shared_precision() {
+        let vars = vec!['x', 'y'];
+        let coarse = MultiSeries::variable(0, vars.clone(), Truncation::TotalDegree(1));
+        let fine = MultiSeries::variable(1, vars.clone(), Truncation::PerVariable(vec![3, 2]));
+
+        let product = coarse.add(&fine).mul(&fine);
+        assert_eq!(product.truncation(), &Truncation::TotalDegree(1));
+        assert!(product.terms().next().is_none());
+        assert_eq!(format!("{}", coarse.add(&fine)), "x + y + O((x, y)^2)");
+
+        let bounded = MultiSeries::variable(0, vars, Truncation::PerVariable(vec![1, 3]));
+        assert_eq!(
+            fine.mul(&bounded).truncation(),
+            &Truncation::PerVariable(vec![1, 2])
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Multiplication of polynomials with coefficients modulo a prime by the
+//! number-theoretic transform. Primes of the form `c 2^k + 1` with `2^k`
+//! at least the product length are transformed directly; any other prime
+//! goes through three such primes and the Chinese remainder theorem, with
+//! products longer than those primes support split into shorter ones.
+
+use crate::modint::{ModInt, inverse_mod};
+
+/// Primes `c 2^k + 1` with `k >= 23` and a primitive root of each. Their
+/// product, about 2^86, exceeds any coefficient of a product of residues
+/// below 2^32 whose shorter operand has at most 2^22 terms.
+const NTT_PRIMES: [(u64, u64); 3] = [(998244353, 3), (167772161, 3), (469762049, 3)];
+
+/// Longest transform all of [`NTT_PRIMES`] support, as
+/// `998244353 = 119 2^23 + 1`. Its products have operands of at most 2^22
+/// terms, as the coefficient bound of the primes requires.
+const MAX_NTT_SIZE: usize = 1 << 23;
+
+/// Shorter operands are multiplied by the schoolbook method.
+const NTT_THRESHOLD: usize = 32;
+
+/// Full product of two non-empty polynomials with residues modulo the
+/// prime `modulus` as coefficients.
+pub(crate) fn convolve(a: &[u64], b: &[u64], modulus: u64) -> Vec<u64> {
+    let len = a.len() + b.len() - 1;
+    if a.len().min(b.len()) < NTT_THRESHOLD {
+        let mut result = vec![0; len];
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                result[i + j] = (result[i + j] + x * y) % modulus;
+            }
+        }
+        return result;
+    }
+
+    let size = len.next_power_of_two();
+    if (modulus - 1).is_multiple_of(size as u64) {
+        return convolve_ntt(a, b, modulus, primitive_root(modulus));
+    }
+    if size > MAX_NTT_SIZE {
+        // Too long for the primes: split the longer operand.
+        let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+        let (low, high) = long.split_at(long.len() / 2);
+        let mut result = convolve(short, low, modulus);
+        result.resize(len, 0);
+        for (r, c) in result[low.len()..]
+            .iter_mut()
+            .zip(convolve(short, high, modulus))
+        {
+            *r = (*r + c) % modulus;
+        }
+        return result;
+    }
+
+    let [r0, r1, r2] = NTT_PRIMES.map(|(p, g)| {
+        let reduce = |v: &[u64]| v.iter().map(|x| x % p).collect::<Vec<_>>();
+        convolve_ntt(&reduce(a), &reduce(b), p, g)
+    });
+    (0..len)
+        .map(|k| garner([r0[k], r1[k], r2[k]], modulus))
+        .collect()
+}
+
+/// The value modulo `modulus` of the integer with the given residues
+/// modulo [`NTT_PRIMES`].
+fn garner(residues: [u64; 3], modulus: u64) -> u64 {
+    let [(m0, _), (m1, _), (m2, _)] = NTT_PRIMES;
+    let [r0, r1, r2] = residues;
+    // x = t0 + t1 m0 + t2 m0 m1.
+    let t0 = r0;
+    let t1 = (r1 + m1 - t0 % m1) % m1 * inverse_mod(m0, m1) % m1;
+    let m0m1 = m0 * m1 % m2;
+    let t2 = (r2 + 2 * m2 - t0 % m2 - t1 * (m0 % m2) % m2) % m2 * inverse_mod(m0m1, m2) % m2;
+
+    let (t0, t1, t2) = (t0 as u128, t1 as u128, t2 as u128);
+    let (m0, m1, modulus) = (m0 as u128, m1 as u128, modulus as u128);
+    ((t0 + t1 * m0 % modulus + t2 * (m0 * m1 % modulus)) % modulus) as u64
+}
+
+fn convolve_ntt(a: &[u64], b: &[u64], p: u64, g: u64) -> Vec<u64> {
+    let len = a.len() + b.len() - 1;
+    let size = len.next_power_of_two();
+    let mut fa = a.to_vec();
+    let mut fb = b.to_vec();
+    fa.resize(size, 0);
+    fb.resize(size, 0);
+    transform(&mut fa, p, g, false);
+    transform(&mut fb, p, g, false);
+    for (x, y) in fa.iter_mut().zip(&fb) {
+        *x = *x * y % p;
+    }
+    transform(&mut fa, p, g, true);
+
+    let scale = inverse_mod(size as u64, p);
+    fa.truncate(len);
+    for x in &mut fa {
+        *x = *x * scale % p;
+    }
+    fa
+}
+
+/// In-place iterative Cooley-Tukey transform of a power-of-two length,
+/// unscaled when inverted.
+fn transform(a: &mut [u64], p: u64, g: u64, invert: bool) {
+    let n = a.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            a.swap(i, j);
+        }
+    }
+
+    // Powers of a primitive n-th root of unity; level `len` uses every
+    // `n/len`-th one.
+    let mut w_n = ModInt::new(g, p).pow((p - 1) / n as u64).value();
+    if invert {
+        w_n = inverse_mod(w_n, p);
+    }
+    let mut roots = Vec::with_capacity(n / 2);
+    let mut w = 1;
+    for _ in 0..n / 2 {
+        roots.push(w);
+        w = w * w_n % p;
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let stride = n / len;
+        for chunk in a.chunks_mut(len) {
+            let (low, high) = chunk.split_at_mut(len / 2);
+            for (k, (u, v)) in low.iter_mut().zip(high).enumerate() {
+                let t = *v * roots[k * stride] % p;
+                *v = if *u >= t { *u - t } else { *u + p - t };
+                *u += t;
+                if *u >= p {
+                    *u -= p;
+                }
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Smallest generator of the multiplicative group modulo the prime `p`.
+fn primitive_root(p: u64) -> u64 {
+    let mut factors = Vec::new();
+    let mut n = p - 1;
+    let mut d = 2;
+    while d * d <= n {
+        if n.is_multiple_of(d) {
+            factors.push(d);
+            while n.is_multiple_of(d) {
+                n /= d;
+            }
+        }
+        d += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+
+    (1..p)
+        .find(|&g| {
+            let g = ModInt::new(g, p);
+            factors.iter().all(|&q| g.pow((p - 1) / q).value() != 1)
+        })
+        .expect("a prime has a primitive root")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schoolbook(a: &[u64], b: &[u64], p: u64) -> Vec<u64> {
+        let mut result = vec![0; a.len() + b.len() - 1];
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                result[i + j] =
+                    ((result[i + j] as u128 + x as u128 * y as u128) % p as u128) as u64;
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn matches_schoolbook_for_any_prime() {
+        for p in [998244353, 1000000007, 4294967291, 65537] {
+            let a: Vec<u64> = (0..300u64).map(|k| (k * k * 7919 + 13) % p).collect();
+            let b: Vec<u64> = (0..201u64).map(|k| (p - 1 - k * 104729 % p) % p).collect();
+            assert_eq!(convolve(&a, &b, p), schoolbook(&a, &b, p), "modulus {}", p);
+        }
+    }
+}
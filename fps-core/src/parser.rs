@@ -1,39 +1,111 @@
-use crate::tokenizer::{FunctionToken, Token};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::functions::FunctionRegistry;
+use crate::tokenizer::{Token, TokenKind};
 use num_rational::BigRational;
+use num_traits::One;
 use thiserror::Error;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Expr {
+pub enum ExprKind {
     Num(BigRational),
+    /// A free single-letter name: the series variable.
     Variable(char),
+    /// A reference to a `let` binding or function parameter.
+    Name(String),
     Add(Box<Expr>, Box<Expr>),
     Sub(Box<Expr>, Box<Expr>),
     Mul(Box<Expr>, Box<Expr>),
     Div(Box<Expr>, Box<Expr>),
     Pow(Box<Expr>, Box<Expr>),
     Neg(Box<Expr>),
-    Sin(Box<Expr>),
-    Cos(Box<Expr>),
-    Exp(Box<Expr>),
-    Log(Box<Expr>),
+    Factorial(Box<Expr>),
+    /// Call of a user-defined or registered function.
+    Call(String, Vec<Expr>),
+    /// Coefficient of `variable^degree` in `body`, written `[x^n] body` or
+    /// `coeff(body, n)`. The latter leaves the variable as the series variable.
+    Coefficient {
+        variable: Option<char>,
+        degree: Box<Expr>,
+        body: Box<Expr>,
+    },
+    /// `let name = value; body`
+    Let {
+        name: String,
+        value: Box<Expr>,
+        body: Box<Expr>,
+    },
+    /// `name(params) = value; body`
+    LetFunction {
+        name: String,
+        params: Vec<String>,
+        value: Box<Expr>,
+        body: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
 }
 
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum ParserError {
-    #[error("Unexpected token: {0:?}")]
+    #[error("Unexpected token: {}", .0.kind)]
     UnexpectedToken(Token),
     #[error("Unexpected end of input")]
-    UnexpectedEof,
+    UnexpectedEof(Span),
+}
+
+impl Diagnostic for ParserError {
+    fn span(&self) -> Span {
+        match self {
+            ParserError::UnexpectedToken(token) => token.span,
+            ParserError::UnexpectedEof(span) => *span,
+        }
+    }
 }
 
 struct Parser<'a> {
     tokens: &'a [Token],
     pos: usize,
+    /// Whether a name refers to a registered function.
+    is_function: &'a dyn Fn(&str) -> bool,
+    /// Names bound at the current position, innermost last. The flag marks
+    /// function definitions, whose names may be followed by an argument list.
+    scope: Vec<(String, bool)>,
+    /// Name whose definition is being parsed. A definition cannot refer to
+    /// itself, so the name is undefined there rather than a series variable.
+    defining: Option<String>,
 }
 
 impl<'a> Parser<'a> {
-    fn new(tokens: &'a [Token]) -> Self {
-        Parser { tokens, pos: 0 }
+    fn new(tokens: &'a [Token], is_function: &'a dyn Fn(&str) -> bool) -> Self {
+        Parser {
+            tokens,
+            pos: 0,
+            is_function,
+            scope: Vec::new(),
+            defining: None,
+        }
+    }
+
+    fn peek_kind(&self, offset: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.pos + offset).map(|t| &t.kind)
+    }
+
+    fn lookup(&self, name: &str) -> Option<bool> {
+        self.scope
+            .iter()
+            .rev()
+            .find(|(bound, _)| bound == name)
+            .map(|(_, is_function)| *is_function)
     }
 
     fn peek(&self) -> Option<&Token> {
@@ -48,93 +120,490 @@ impl<'a> Parser<'a> {
         token
     }
 
-    fn parse_primary(&mut self) -> Result<Expr, ParserError> {
+    fn unexpected(&self, token: Option<&Token>) -> ParserError {
+        match token {
+            Some(t) => ParserError::UnexpectedToken(t.clone()),
+            None => {
+                let end = self.tokens.last().map_or(0, |t| t.span.end);
+                ParserError::UnexpectedEof(Span::point(end))
+            }
+        }
+    }
+
+    /// Consumes a token of the given kind and returns its span.
+    fn expect(&mut self, kind: TokenKind) -> Result<Span, ParserError> {
+        match self.consume() {
+            Some(token) if token.kind == kind => Ok(token.span),
+            t => {
+                let t = t.cloned();
+                Err(self.unexpected(t.as_ref()))
+            }
+        }
+    }
+
+    fn expect_rparen(&mut self) -> Result<Span, ParserError> {
+        self.expect(TokenKind::RParen)
+    }
+
+    fn expect_ident(&mut self) -> Result<String, ParserError> {
         match self.consume() {
-            Some(Token::Num(n)) => Ok(Expr::Num(n.clone())),
-            Some(Token::Variable(c)) => Ok(Expr::Variable(*c)),
-            Some(Token::Function(func)) => {
-                let func = *func;
-                match self.consume() {
-                    Some(Token::LParen) => {
-                        let expr = self.parse_expr(0)?;
-                        match self.consume() {
-                            Some(Token::RParen) => match func {
-                                FunctionToken::Sin => Ok(Expr::Sin(Box::new(expr))),
-                                FunctionToken::Cos => Ok(Expr::Cos(Box::new(expr))),
-                                FunctionToken::Exp => Ok(Expr::Exp(Box::new(expr))),
-                                FunctionToken::Log => Ok(Expr::Log(Box::new(expr))),
-                            },
-                            Some(t) => Err(ParserError::UnexpectedToken(t.clone())),
-                            None => Err(ParserError::UnexpectedEof),
-                        }
-                    }
-                    Some(t) => Err(ParserError::UnexpectedToken(t.clone())),
-                    None => Err(ParserError::UnexpectedEof),
+            Some(Token {
+                kind: TokenKind::Ident(name),
+                ..
+            }) => Ok(name.clone()),
+            t => {
+                let t = t.cloned();
+                Err(self.unexpected(t.as_ref()))
+            }
+        }
+    }
+
+    /// Whether the upcoming tokens read `name(a, b, ...) =`.
+    fn at_function_definition(&self) -> bool {
+        if !matches!(self.peek_kind(0), Some(TokenKind::Ident(_)))
+            || self.peek_kind(1) != Some(&TokenKind::LParen)
+        {
+            return false;
+        }
+
+        let mut offset = 2;
+        loop {
+            if !matches!(self.peek_kind(offset), Some(TokenKind::Ident(_))) {
+                return false;
+            }
+            match self.peek_kind(offset + 1) {
+                Some(TokenKind::Comma) => offset += 2,
+                Some(TokenKind::RParen) => {
+                    return self.peek_kind(offset + 2) == Some(&TokenKind::Equals);
                 }
+                _ => return false,
             }
-            Some(Token::LParen) => {
-                let expr = self.parse_expr(0)?;
-                match self.consume() {
-                    Some(Token::RParen) => Ok(expr),
-                    Some(t) => Err(ParserError::UnexpectedToken(t.clone())),
-                    None => Err(ParserError::UnexpectedEof),
+        }
+    }
+
+    /// Parses `(a, b, ...)` after a function name.
+    fn parse_params(&mut self) -> Result<Vec<String>, ParserError> {
+        self.expect(TokenKind::LParen)?;
+        let mut params = vec![self.expect_ident()?];
+        while self.peek_kind(0) == Some(&TokenKind::Comma) {
+            self.consume();
+            params.push(self.expect_ident()?);
+        }
+        self.expect_rparen()?;
+        Ok(params)
+    }
+
+    /// Parses a sequence of definitions separated by `;` and ending in an
+    /// expression. Each definition scopes over everything after it.
+    fn parse_program(&mut self) -> Result<Expr, ParserError> {
+        let start = match self.peek() {
+            Some(token) => token.span,
+            None => return Err(self.unexpected(None)),
+        };
+
+        let is_let = self.peek_kind(0) == Some(&TokenKind::Let);
+        if !is_let && !self.at_function_definition() {
+            return self.parse_expr(0);
+        }
+        if is_let {
+            self.consume();
+        }
+
+        let name = self.expect_ident()?;
+        let params = if self.peek_kind(0) == Some(&TokenKind::LParen) {
+            Some(self.parse_params()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Equals)?;
+
+        let scope_len = self.scope.len();
+        if let Some(params) = &params {
+            self.scope
+                .extend(params.iter().map(|param| (param.clone(), false)));
+        }
+        self.defining = Some(name.clone());
+        let value = self.parse_expr(0);
+        self.defining = None;
+        self.scope.truncate(scope_len);
+        let value = Box::new(value?);
+        self.expect(TokenKind::Semicolon)?;
+
+        self.scope.push((name.clone(), params.is_some()));
+        let body = self.parse_program();
+        self.scope.truncate(scope_len);
+        let body = Box::new(body?);
+
+        let span = start.to(body.span);
+        let kind = match params {
+            Some(params) => ExprKind::LetFunction {
+                name,
+                params,
+                value,
+                body,
+            },
+            None => ExprKind::Let { name, value, body },
+        };
+        Ok(Expr::new(kind, span))
+    }
+
+    fn parse_call(&mut self, name: String, name_span: Span) -> Result<Expr, ParserError> {
+        self.expect(TokenKind::LParen)?;
+        let mut args = vec![self.parse_expr(0)?];
+        while self.peek_kind(0) == Some(&TokenKind::Comma) {
+            self.consume();
+            args.push(self.parse_expr(0)?);
+        }
+        let span = name_span.to(self.expect_rparen()?);
+        Ok(Expr::new(ExprKind::Call(name, args), span))
+    }
+
+    /// Parses `[x^n] body` after the opening bracket. `[x]` is short for
+    /// `[x^1]`. The body extends over products and quotients but not sums,
+    /// so `[x^2] f + g` adds `g` to the extracted coefficient.
+    fn parse_coefficient(&mut self, open: Span) -> Result<Expr, ParserError> {
+        let variable = match self.consume() {
+            Some(Token {
+                kind: TokenKind::Ident(name),
+                span,
+            }) if name.chars().count() == 1 => (name.chars().next().unwrap(), *span),
+            t => {
+                let t = t.cloned();
+                return Err(self.unexpected(t.as_ref()));
+            }
+        };
+
+        let degree = if self.peek_kind(0) == Some(&TokenKind::Caret) {
+            self.consume();
+            self.parse_expr(0)?
+        } else {
+            Expr::new(ExprKind::Num(BigRational::one()), variable.1)
+        };
+        self.expect(TokenKind::RBracket)?;
+
+        let body = self.parse_expr(3)?;
+        let span = open.to(body.span);
+        let kind = ExprKind::Coefficient {
+            variable: Some(variable.0),
+            degree: Box::new(degree),
+            body: Box::new(body),
+        };
+        Ok(Expr::new(kind, span))
+    }
+
+    /// Parses `coeff(body, n)` after the name.
+    fn parse_coeff_call(&mut self, name_span: Span) -> Result<Expr, ParserError> {
+        self.expect(TokenKind::LParen)?;
+        let body = self.parse_expr(0)?;
+        self.expect(TokenKind::Comma)?;
+        let degree = self.parse_expr(0)?;
+        let span = name_span.to(self.expect_rparen()?);
+        let kind = ExprKind::Coefficient {
+            variable: None,
+            degree: Box::new(degree),
+            body: Box::new(body),
+        };
+        Ok(Expr::new(kind, span))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParserError> {
+        let token = match self.consume() {
+            Some(token) => token.clone(),
+            None => return Err(self.unexpected(None)),
+        };
+
+        match token.kind {
+            TokenKind::Num(n) => Ok(Expr::new(ExprKind::Num(n), token.span)),
+            TokenKind::Ident(name)
+                if name == "coeff"
+                    && self.peek_kind(0) == Some(&TokenKind::LParen)
+                    && self.lookup(&name).is_none() =>
+            {
+                self.parse_coeff_call(token.span)
+            }
+            TokenKind::Ident(name) => {
+                let is_call = self.peek_kind(0) == Some(&TokenKind::LParen)
+                    && match self.lookup(&name) {
+                        Some(is_function) => is_function,
+                        None => (self.is_function)(&name),
+                    };
+                if is_call {
+                    self.parse_call(name, token.span)
+                } else if self.lookup(&name).is_none()
+                    && self.defining.as_ref() != Some(&name)
+                    && name.chars().count() == 1
+                {
+                    let c = name.chars().next().unwrap();
+                    Ok(Expr::new(ExprKind::Variable(c), token.span))
+                } else {
+                    Ok(Expr::new(ExprKind::Name(name), token.span))
                 }
             }
-            Some(Token::Minus) => {
+            TokenKind::LParen => {
+                let expr = self.parse_expr(0)?;
+                let span = token.span.to(self.expect_rparen()?);
+                Ok(Expr::new(expr.kind, span))
+            }
+            TokenKind::LBracket => self.parse_coefficient(token.span),
+            TokenKind::Minus => {
                 let expr = self.parse_expr(5)?; // Unary minus precedence
-                Ok(Expr::Neg(Box::new(expr)))
+                let span = token.span.to(expr.span);
+                Ok(Expr::new(ExprKind::Neg(Box::new(expr)), span))
             }
-            Some(t) => Err(ParserError::UnexpectedToken(t.clone())),
-            None => Err(ParserError::UnexpectedEof),
+            _ => Err(ParserError::UnexpectedToken(token)),
         }
     }
 
     fn parse_expr(&mut self, min_bp: u8) -> Result<Expr, ParserError> {
         let mut lhs = self.parse_primary()?;
 
-        loop {
-            let op = match self.peek() {
-                Some(op) => op,
-                None => break,
-            };
+        while let Some(op) = self.peek() {
+            if let Some(l_bp) = postfix_binding_power(&op.kind) {
+                if l_bp < min_bp {
+                    break;
+                }
+                let span = lhs.span.to(op.span);
+                self.consume();
+                lhs = Expr::new(ExprKind::Factorial(Box::new(lhs)), span);
+                continue;
+            }
 
-            let (l_bp, r_bp) = match infix_binding_power(op) {
-                Some(bp) => bp,
-                None => break,
+            // A name directly followed by `(` that is not a function call is
+            // an implicit product, e.g. `x(1 + x)`.
+            let implicit_star = op.kind == TokenKind::LParen;
+            let (l_bp, r_bp) = if implicit_star {
+                (3, 4)
+            } else {
+                match infix_binding_power(&op.kind) {
+                    Some(bp) => bp,
+                    None => break,
+                }
             };
 
             if l_bp < min_bp {
                 break;
             }
 
-            let op = self.consume().unwrap().clone();
+            let op = if implicit_star {
+                TokenKind::Star
+            } else {
+                self.consume().unwrap().kind.clone()
+            };
 
             let rhs = self.parse_expr(r_bp)?;
+            let span = lhs.span.to(rhs.span);
 
-            lhs = match op {
-                Token::Plus => Expr::Add(Box::new(lhs), Box::new(rhs)),
-                Token::Minus => Expr::Sub(Box::new(lhs), Box::new(rhs)),
-                Token::Star => Expr::Mul(Box::new(lhs), Box::new(rhs)),
-                Token::Slash => Expr::Div(Box::new(lhs), Box::new(rhs)),
-                Token::Caret => Expr::Pow(Box::new(lhs), Box::new(rhs)),
+            let kind = match op {
+                TokenKind::Plus => ExprKind::Add(Box::new(lhs), Box::new(rhs)),
+                TokenKind::Minus => ExprKind::Sub(Box::new(lhs), Box::new(rhs)),
+                TokenKind::Star => ExprKind::Mul(Box::new(lhs), Box::new(rhs)),
+                TokenKind::Slash => ExprKind::Div(Box::new(lhs), Box::new(rhs)),
+                TokenKind::Caret => ExprKind::Pow(Box::new(lhs), Box::new(rhs)),
                 _ => unreachable!(),
             };
+            lhs = Expr::new(kind, span);
         }
 
         Ok(lhs)
     }
 }
 
-fn infix_binding_power(op: &Token) -> Option<(u8, u8)> {
+fn infix_binding_power(op: &TokenKind) -> Option<(u8, u8)> {
     match op {
-        Token::Plus | Token::Minus => Some((1, 2)),
-        Token::Star | Token::Slash => Some((3, 4)),
-        Token::Caret => Some((6, 5)), // Right-associative
+        TokenKind::Plus | TokenKind::Minus => Some((1, 2)),
+        TokenKind::Star | TokenKind::Slash => Some((3, 4)),
+        TokenKind::Caret => Some((6, 5)), // Right-associative
         _ => None,
     }
 }
 
+fn postfix_binding_power(op: &TokenKind) -> Option<u8> {
+    match op {
+        TokenKind::Bang => Some(7), // Binds tighter than `^`
+        _ => None,
+    }
+}
+
+/// Parses `tokens` using the built-in functions of [`FunctionRegistry::standard`].
 pub fn parse(tokens: &[Token]) -> Result<Expr, ParserError> {
-    let mut parser = Parser::new(tokens);
-    parser.parse_expr(0)
+    parse_with(
+        tokens,
+        &FunctionRegistry::<crate::series::Series>::standard(),
+    )
+}
+
+/// Parses `tokens`, treating the functions in `registry` as callable names.
+pub fn parse_with<V>(
+    tokens: &[Token],
+    registry: &FunctionRegistry<V>,
+) -> Result<Expr, ParserError> {
+    let is_function = |name: &str| registry.contains(name);
+    let mut parser = Parser::new(tokens, &is_function);
+    let expr = parser.parse_program()?;
+    match parser.peek() {
+        Some(token) => Err(ParserError::UnexpectedToken(token.clone())),
+        None => Ok(expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tokenize;
+
+    fn parse_str(input: &str) -> Result<Expr, ParserError> {
+        parse(&tokenize(input).expect("tokenize"))
+    }
+
+    #[test]
+    fn nodes_cover_their_source() {
+        let expr = parse_str("2 * (1 + x)!").expect("parse");
+        assert_eq!(expr.span, Span::new(0, 12));
+
+        let ExprKind::Mul(lhs, rhs) = expr.kind else {
+            panic!("expected multiplication, got {:?}", expr.kind);
+        };
+        assert_eq!(lhs.span, Span::new(0, 1));
+        assert_eq!(rhs.span, Span::new(4, 12));
+    }
+
+    #[test]
+    fn reports_trailing_tokens() {
+        let error = parse_str("1 + x)").unwrap_err();
+        assert_eq!(error.span(), Span::new(5, 6));
+    }
+
+    #[test]
+    fn reports_eof_after_last_token() {
+        let error = parse_str("sin(x").unwrap_err();
+        assert_eq!(error, ParserError::UnexpectedEof(Span::point(5)));
+    }
+
+    #[test]
+    fn let_binding_scopes_over_body() {
+        let expr = parse_str("let g = 1/(1-x); g^2 + g").expect("parse");
+        assert_eq!(expr.span, Span::new(0, 24));
+
+        let ExprKind::Let { name, value, body } = expr.kind else {
+            panic!("expected let binding, got {:?}", expr.kind);
+        };
+        assert_eq!(name, "g");
+        assert_eq!(value.span, Span::new(8, 15));
+
+        let ExprKind::Add(lhs, rhs) = body.kind else {
+            panic!("expected addition, got {:?}", body.kind);
+        };
+        assert!(matches!(rhs.kind, ExprKind::Name(ref n) if n == "g"));
+        let ExprKind::Pow(base, _) = lhs.kind else {
+            panic!("expected power, got {:?}", lhs.kind);
+        };
+        assert!(matches!(base.kind, ExprKind::Name(ref n) if n == "g"));
+    }
+
+    #[test]
+    fn function_definitions_and_calls() {
+        let expr = parse_str("f(t) = 1/(1-t); f(x^2) t").expect("parse");
+        let ExprKind::LetFunction {
+            name, params, body, ..
+        } = expr.kind
+        else {
+            panic!("expected function definition, got {:?}", expr.kind);
+        };
+        assert_eq!(name, "f");
+        assert_eq!(params, vec!["t".to_string()]);
+
+        // Outside the definition `t` is free again.
+        let ExprKind::Mul(call, t) = body.kind else {
+            panic!("expected product, got {:?}", body.kind);
+        };
+        assert!(
+            matches!(call.kind, ExprKind::Call(ref n, ref args) if n == "f" && args.len() == 1)
+        );
+        assert_eq!(t.kind, ExprKind::Variable('t'));
+    }
+
+    #[test]
+    fn registered_functions_become_calls() {
+        let expr = parse_str("exp(x) + sin(x, 2)").expect("parse");
+        let ExprKind::Add(lhs, rhs) = expr.kind else {
+            panic!("expected addition, got {:?}", expr.kind);
+        };
+        assert!(
+            matches!(lhs.kind, ExprKind::Call(ref n, ref args) if n == "exp" && args.len() == 1)
+        );
+        assert!(
+            matches!(rhs.kind, ExprKind::Call(ref n, ref args) if n == "sin" && args.len() == 2)
+        );
+        assert_eq!(rhs.span, Span::new(9, 18));
+
+        let empty = FunctionRegistry::<crate::series::Series>::new();
+        let tokens = tokenize("exp(x)").expect("tokenize");
+        let expr = parse_with(&tokens, &empty).expect("parse");
+        assert!(matches!(expr.kind, ExprKind::Mul(_, _)));
+    }
+
+    #[test]
+    fn let_binding_shadows_registered_function() {
+        let expr = parse_str("let exp = 2; exp(x)").expect("parse");
+        let ExprKind::Let { body, .. } = expr.kind else {
+            panic!("expected let binding, got {:?}", expr.kind);
+        };
+        assert!(matches!(body.kind, ExprKind::Mul(_, _)));
+    }
+
+    #[test]
+    fn unbound_name_before_paren_is_a_product() {
+        let expr = parse_str("x(1 + x)").expect("parse");
+        assert!(matches!(expr.kind, ExprKind::Mul(_, _)));
+    }
+
+    #[test]
+    fn parses_coefficient_extraction() {
+        let expr = parse_str("[x^2] 1/(1-x) + 1").expect("parse");
+        let ExprKind::Add(lhs, _) = expr.kind else {
+            panic!("expected addition, got {:?}", expr.kind);
+        };
+        let ExprKind::Coefficient { variable, body, .. } = lhs.kind else {
+            panic!("expected coefficient, got {:?}", lhs.kind);
+        };
+        assert_eq!(variable, Some('x'));
+        assert!(matches!(body.kind, ExprKind::Div(_, _)));
+        assert_eq!(lhs.span, Span::new(0, 13));
+
+        let expr = parse_str("coeff(exp(x), 3)").expect("parse");
+        assert!(matches!(
+            expr.kind,
+            ExprKind::Coefficient { variable: None, .. }
+        ));
+
+        let error = parse_str("[xy^2] x").unwrap_err();
+        assert_eq!(error.span(), Span::new(1, 3));
+    }
+
+    #[test]
+    fn definition_cannot_refer_to_itself() {
+        let expr = parse_str("let a = a; a").expect("parse");
+        let ExprKind::Let { value, .. } = expr.kind else {
+            panic!("expected let binding, got {:?}", expr.kind);
+        };
+        assert_eq!(value.kind, ExprKind::Name("a".to_string()));
+
+        let expr = parse_str("f(t) = f(t); f(x)").expect("parse");
+        let ExprKind::LetFunction { value, .. } = expr.kind else {
+            panic!("expected function definition, got {:?}", expr.kind);
+        };
+        let ExprKind::Mul(lhs, _) = value.kind else {
+            panic!("expected product, got {:?}", value.kind);
+        };
+        assert_eq!(lhs.kind, ExprKind::Name("f".to_string()));
+    }
+
+    #[test]
+    fn definition_requires_semicolon() {
+        let error = parse_str("let g = x g").unwrap_err();
+        assert_eq!(error, ParserError::UnexpectedEof(Span::point(11)));
+
+        let error = parse_str("let g = x, g").unwrap_err();
+        assert_eq!(error.span(), Span::new(9, 10));
+    }
 }
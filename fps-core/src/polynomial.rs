@@ -0,0 +1,396 @@
+use num_bigint::BigInt;
+use num_rational::{BigRational, Rational64};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::fmt;
+
+use crate::dense;
+use crate::evaluator::EvalError;
+use crate::factor::{self, gcd, lcm};
+use crate::series::{DEFAULT_VARIABLE, Series, format_term};
+
+/// Degree above which powers are left to truncated series instead.
+const MAX_DEGREE: usize = 4096;
+
+/// An exact univariate polynomial with rational coefficients.
+///
+/// Coefficients are stored from the constant term up, without trailing
+/// zeros, so the zero polynomial has no coefficients at all.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    coeffs: Vec<BigRational>,
+    variable: char,
+}
+
+impl Polynomial {
+    pub fn new(coeffs: Vec<BigRational>) -> Self {
+        let mut polynomial = Self {
+            coeffs,
+            variable: DEFAULT_VARIABLE,
+        };
+        polynomial.trim();
+        polynomial
+    }
+
+    pub fn zero() -> Self {
+        Self::new(Vec::new())
+    }
+
+    pub fn one() -> Self {
+        Self::constant(BigRational::one())
+    }
+
+    pub fn constant(value: BigRational) -> Self {
+        Self::new(vec![value])
+    }
+
+    /// The polynomial `x`.
+    pub fn variable() -> Self {
+        Self::new(vec![BigRational::zero(), BigRational::one()])
+    }
+
+    /// The same polynomial, displayed in terms of `variable`.
+    pub fn with_variable(mut self, variable: char) -> Self {
+        self.variable = variable;
+        self
+    }
+
+    pub fn variable_name(&self) -> char {
+        self.variable
+    }
+
+    /// Polynomial with the given coefficients and the variable of `self`.
+    fn with_coeffs(&self, coeffs: Vec<BigRational>) -> Polynomial {
+        Polynomial::new(coeffs).with_variable(self.variable)
+    }
+
+    fn trim(&mut self) {
+        while self.coeffs.last().is_some_and(Zero::is_zero) {
+            self.coeffs.pop();
+        }
+    }
+
+    /// Coefficients from the constant term up to the leading one.
+    pub fn coeffs(&self) -> &[BigRational] {
+        &self.coeffs
+    }
+
+    /// Coefficient of `x^n`.
+    pub fn coefficient(&self, n: usize) -> BigRational {
+        self.coeffs
+            .get(n)
+            .cloned()
+            .unwrap_or_else(BigRational::zero)
+    }
+
+    /// Degree, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coeffs.len().checked_sub(1)
+    }
+
+    pub fn leading_coefficient(&self) -> BigRational {
+        self.coeffs
+            .last()
+            .cloned()
+            .unwrap_or_else(BigRational::zero)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.coeffs.len() <= 1
+    }
+
+    pub fn constant_term(&self) -> BigRational {
+        self.coefficient(0)
+    }
+
+    pub fn add(&self, other: &Polynomial) -> Polynomial {
+        let len = self.coeffs.len().max(other.coeffs.len());
+        let coeffs = (0..len)
+            .map(|k| self.coefficient(k) + other.coefficient(k))
+            .collect();
+        self.with_coeffs(coeffs)
+    }
+
+    pub fn neg(&self) -> Polynomial {
+        self.with_coeffs(self.coeffs.iter().map(|c| -c).collect())
+    }
+
+    pub fn sub(&self, other: &Polynomial) -> Polynomial {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Polynomial) -> Polynomial {
+        if self.is_zero() || other.is_zero() {
+            return self.with_coeffs(Vec::new());
+        }
+
+        let len = self.coeffs.len() + other.coeffs.len() - 1;
+        self.with_coeffs(dense::mul_rational(&self.coeffs, &other.coeffs, len))
+    }
+
+    pub fn scale(&self, scalar: &BigRational) -> Polynomial {
+        self.with_coeffs(self.coeffs.iter().map(|c| c * scalar).collect())
+    }
+
+    /// Quotient and remainder of division by `divisor`, with the remainder
+    /// of lower degree than `divisor`.
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Polynomial, Polynomial), EvalError> {
+        let Some(divisor_degree) = divisor.degree() else {
+            return Err(EvalError::DivisionByZero);
+        };
+        let lead = divisor.leading_coefficient();
+
+        let mut remainder = self.coeffs.clone();
+        let quotient_len = (remainder.len() + 1).saturating_sub(divisor.coeffs.len());
+        let mut quotient = vec![BigRational::zero(); quotient_len];
+        for k in (0..quotient_len).rev() {
+            let factor = &remainder[k + divisor_degree] / &lead;
+            for (i, d) in divisor.coeffs.iter().enumerate() {
+                remainder[k + i] -= &factor * d;
+            }
+            quotient[k] = factor;
+        }
+        remainder.truncate(divisor_degree);
+        Ok((self.with_coeffs(quotient), self.with_coeffs(remainder)))
+    }
+
+    /// Exact quotient, failing if `divisor` leaves a remainder.
+    pub fn div(&self, divisor: &Polynomial) -> Result<Polynomial, EvalError> {
+        let (quotient, remainder) = self.div_rem(divisor)?;
+        if !remainder.is_zero() {
+            return Err(EvalError::NotPolynomial("division"));
+        }
+        Ok(quotient)
+    }
+
+    /// Monic greatest common divisor; zero only if both are zero.
+    ///
+    /// Coprime polynomials are usually recognized modulo a prime. Otherwise
+    /// each remainder is scaled to coprime integer coefficients, which keeps
+    /// them from growing into huge fractions as plain Euclid's do.
+    pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+        if factor::coprime_modulo_prime(self, other) {
+            return Polynomial::one().with_variable(self.variable);
+        }
+        let (mut a, mut b) = (self.primitive_part(), other.primitive_part());
+        while !b.is_zero() {
+            let (_, remainder) = a.div_rem(&b).expect("divisor is non-zero");
+            a = b;
+            b = remainder.primitive_part();
+        }
+        a.monic()
+    }
+
+    /// `self` scaled to coprime integer coefficients, keeping their signs.
+    pub fn primitive_part(&self) -> Polynomial {
+        if self.is_zero() {
+            return self.clone();
+        }
+        let numerators = self
+            .coeffs
+            .iter()
+            .fold(BigInt::zero(), |acc, c| gcd(&acc, c.numer()));
+        let denominators = self
+            .coeffs
+            .iter()
+            .fold(BigInt::one(), |acc, c| lcm(&acc, c.denom()));
+        self.scale(&BigRational::new(denominators, numerators))
+    }
+
+    /// `self` divided by its leading coefficient.
+    pub fn monic(&self) -> Polynomial {
+        if self.is_zero() {
+            return self.clone();
+        }
+        self.scale(&(BigRational::one() / self.leading_coefficient()))
+    }
+
+    pub fn powi(&self, exponent: i64) -> Result<Polynomial, EvalError> {
+        if exponent < 0 {
+            if !self.is_constant() {
+                return Err(EvalError::NotPolynomial("negative power"));
+            }
+            let value = self.constant_term();
+            if value.is_zero() {
+                return Err(EvalError::DivisionByZero);
+            }
+            return Polynomial::constant(BigRational::one() / value)
+                .with_variable(self.variable)
+                .powi(-exponent);
+        }
+
+        let degree = self.degree().unwrap_or(0);
+        let too_large = exponent
+            .to_usize()
+            .and_then(|e| degree.checked_mul(e))
+            .is_none_or(|d| d > MAX_DEGREE);
+        if degree > 0 && too_large {
+            return Err(EvalError::ExponentTooLarge);
+        }
+
+        let mut result = self.with_coeffs(vec![BigRational::one()]);
+        let mut base = self.clone();
+        let mut exp = exponent;
+        while exp > 0 {
+            if exp % 2 == 1 {
+                result = result.mul(&base);
+            }
+            exp /= 2;
+            if exp > 0 {
+                base = base.mul(&base);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Value at `x`, by Horner's rule.
+    pub fn eval(&self, x: &BigRational) -> BigRational {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(BigRational::zero(), |acc, c| acc * x + c)
+    }
+
+    /// `self(inner)`.
+    pub fn compose(&self, inner: &Polynomial) -> Polynomial {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(inner.with_coeffs(Vec::new()), |acc, c| {
+                acc.mul(inner).add(&Polynomial::constant(c.clone()))
+            })
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        let coeffs = self
+            .coeffs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, c)| c * BigInt::from(k))
+            .collect();
+        self.with_coeffs(coeffs)
+    }
+
+    /// Antiderivative with zero constant term.
+    pub fn integral(&self) -> Polynomial {
+        let mut coeffs = vec![BigRational::zero()];
+        coeffs.extend(
+            self.coeffs
+                .iter()
+                .enumerate()
+                .map(|(k, c)| c / BigInt::from(k + 1)),
+        );
+        self.with_coeffs(coeffs)
+    }
+
+    /// The polynomial as a series known up to `O(x^(max_degree+1))`.
+    pub fn to_series(&self, max_degree: usize) -> Series {
+        Series::from_coeffs(&self.coeffs, max_degree).with_variable(self.variable)
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let base = self.variable.to_string();
+        let mut first = true;
+
+        for (k, coeff) in self.coeffs.iter().enumerate() {
+            if coeff.is_zero() {
+                continue;
+            }
+
+            let term = format_term(&coeff.abs(), Rational64::from_integer(k as i64), &base);
+            if first {
+                if coeff.is_negative() {
+                    write!(f, "-{}", term)?;
+                } else {
+                    write!(f, "{}", term)?;
+                }
+                first = false;
+            } else if coeff.is_negative() {
+                write!(f, " - {}", term)?;
+            } else {
+                write!(f, " + {}", term)?;
+            }
+        }
+
+        if first {
+            write!(f, "0")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(coeffs: &[i64]) -> Polynomial {
+        Polynomial::new(
+            coeffs
+                .iter()
+                .map(|&c| BigRational::from_integer(c.into()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn arithmetic_and_display() {
+        let p = poly(&[1, 1]).powi(3).unwrap();
+        assert_eq!(format!("{}", p), "1 + 3 x + 3 x^2 + x^3");
+        assert_eq!(p.sub(&p), Polynomial::zero());
+        assert_eq!(format!("{}", Polynomial::zero()), "0");
+        assert_eq!(
+            format!("{}", poly(&[0, -2, 0, 1]).with_variable('t')),
+            "-2 t + t^3"
+        );
+        assert_eq!(
+            poly(&[1, 1]).powi(-1),
+            Err(EvalError::NotPolynomial("negative power"))
+        );
+    }
+
+    #[test]
+    fn division_with_remainder() {
+        let (quotient, remainder) = poly(&[1, 0, 0, 1]).div_rem(&poly(&[1, 1])).unwrap();
+        assert_eq!(quotient, poly(&[1, -1, 1]));
+        assert_eq!(remainder, Polynomial::zero());
+
+        let (quotient, remainder) = poly(&[1, 0, 1]).div_rem(&poly(&[0, 2])).unwrap();
+        assert_eq!(format!("{}", quotient), "1/2 x");
+        assert_eq!(remainder, poly(&[1]));
+
+        assert_eq!(
+            poly(&[1]).div_rem(&Polynomial::zero()),
+            Err(EvalError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn gcd_is_monic() {
+        let a = poly(&[-1, 0, 1]).scale(&BigRational::from_integer(3.into())); // 3 (x^2 - 1)
+        let b = poly(&[1, 2, 1]); // (x + 1)^2
+        assert_eq!(a.gcd(&b), poly(&[1, 1]));
+        assert_eq!(a.gcd(&poly(&[2, 1])), poly(&[1]));
+        assert_eq!(a.gcd(&Polynomial::zero()), poly(&[-1, 0, 1]));
+
+        let power = |p: &[i64], n| poly(p).powi(n).unwrap();
+        let a = power(&[1, -1], 24).mul(&power(&[1, 2], 12));
+        let b = power(&[1, -1], 12).mul(&power(&[1, 3], 12));
+        assert_eq!(a.gcd(&b), power(&[1, -1], 12));
+    }
+
+    #[test]
+    fn evaluates_and_composes() {
+        let p = poly(&[1, -3, 0, 2]);
+        let half = BigRational::new(1.into(), 2.into());
+        assert_eq!(p.eval(&half), BigRational::new((-1).into(), 4.into()));
+        assert_eq!(p.compose(&poly(&[0, 1])), p);
+        assert_eq!(poly(&[0, 0, 1]).compose(&poly(&[1, 1])), poly(&[1, 2, 1]));
+        assert_eq!(p.derivative().integral(), poly(&[0, -3, 0, 2]));
+    }
+}
@@ -0,0 +1,201 @@
+//! Evaluation of an input string as the front ends show it.
+//!
+//! The options select how an expression is evaluated: as a multivariate
+//! series, in factored form, modulo a prime, in closed form, as an exact
+//! polynomial or as a truncated series. fps-cli and fps-web both call
+//! [`run`], so they pick the same mode for the same input.
+
+use thiserror::Error;
+
+use crate::diagnostic;
+use crate::evaluator::{self, EvalError, SpannedEvalError};
+use crate::functions::FunctionRegistry;
+use crate::multivariate::Truncation;
+use crate::parser::{self, ParserError};
+use crate::series::{DEFAULT_VARIABLE, ExpansionPoint};
+use crate::tokenizer::{self, TokenizerError};
+
+/// How to evaluate and print an expression.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Maximum degree of the resulting series.
+    pub max_degree: usize,
+    /// Series variable; by default the only variable in the expression.
+    pub variable: Option<char>,
+    /// Point to expand around; by default 0.
+    pub point: Option<ExpansionPoint>,
+    /// Print a rational result in closed form instead of as a series.
+    pub closed_form: bool,
+    /// Per-variable degree bounds for multivariate expressions, in
+    /// alphabetical variable order, instead of a bound on the total degree.
+    pub bounds: Option<Vec<usize>>,
+    /// Compute with coefficients modulo this prime.
+    pub modulus: Option<u64>,
+    /// Compute modulo several primes and recover the exact rationals.
+    pub multimodular: bool,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum QueryError {
+    #[error(transparent)]
+    Tokenizer(#[from] TokenizerError),
+    #[error(transparent)]
+    Parser(#[from] ParserError),
+    #[error(transparent)]
+    Eval(#[from] SpannedEvalError),
+    #[error("factor(...) cannot be combined with {0}")]
+    FactorConflict(&'static str),
+}
+
+impl QueryError {
+    /// The error message, with the offending part of `input` underlined
+    /// where the error points at one.
+    pub fn render(&self, input: &str) -> String {
+        match self {
+            QueryError::Tokenizer(error) => diagnostic::render(input, error),
+            QueryError::Parser(error) => diagnostic::render(input, error),
+            QueryError::Eval(error) => diagnostic::render(input, error),
+            QueryError::FactorConflict(_) => self.to_string(),
+        }
+    }
+}
+
+/// Evaluates `input` with the standard functions and formats the result.
+pub fn run(input: &str, options: &Options) -> Result<String, QueryError> {
+    let tokens = tokenizer::tokenize(input)?;
+    let ast = parser::parse(&tokens)?;
+    let max_degree = options.max_degree;
+
+    let variables = evaluator::collect_variables(&ast);
+    if options.variable.is_none()
+        && options.point.is_none()
+        && !options.closed_form
+        && options.modulus.is_none()
+        && (variables.len() > 1 || options.bounds.is_some())
+    {
+        let truncation = match &options.bounds {
+            Some(bounds) => Truncation::PerVariable(bounds.clone()),
+            None => Truncation::TotalDegree(max_degree),
+        };
+        let functions = FunctionRegistry::standard();
+        let series = evaluator::evaluate_multivariate(&ast, &variables, &truncation, &functions)?;
+        return Ok(series.to_string());
+    }
+
+    let variable = match options.variable {
+        Some(variable) => variable,
+        None => evaluator::detect_variable(&ast)?.unwrap_or(DEFAULT_VARIABLE),
+    };
+    if evaluator::is_factor_query(&ast) {
+        if options.point.is_some() {
+            return Err(QueryError::FactorConflict("an expansion point"));
+        }
+        if options.modulus.is_some() {
+            return Err(QueryError::FactorConflict("modular arithmetic"));
+        }
+        if options.multimodular {
+            return Err(QueryError::FactorConflict("multimodular evaluation"));
+        }
+        let polynomial =
+            evaluator::evaluate_polynomial(&ast, variable, &FunctionRegistry::standard())?;
+        return Ok(polynomial.factor().to_string());
+    }
+
+    if let Some(modulus) = options.modulus {
+        let series = evaluator::evaluate_modular(
+            &ast,
+            variable,
+            modulus,
+            max_degree,
+            &FunctionRegistry::standard(),
+        )?;
+        if evaluator::is_coefficient_query(&ast) {
+            return Ok(series.constant_term().to_string());
+        }
+        return Ok(series.to_string());
+    }
+
+    if options.closed_form {
+        let rational = evaluator::evaluate_rational(&ast, variable, &FunctionRegistry::standard())?;
+        return Ok(rational.to_string());
+    }
+
+    let point = options.point.clone().unwrap_or_default();
+    // Exact polynomials are printed in full, without an O-term. Expressions
+    // that are not small polynomials fall through to the series path.
+    if point == ExpansionPoint::default() {
+        match evaluator::evaluate_small_polynomial(
+            &ast,
+            variable,
+            max_degree,
+            &FunctionRegistry::standard(),
+        ) {
+            Ok(polynomial) => return Ok(polynomial.to_string()),
+            Err(SpannedEvalError {
+                error: EvalError::NotPolynomial(_) | EvalError::ExponentTooLarge,
+                ..
+            }) => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    let series = if options.multimodular {
+        evaluator::evaluate_multimodular(&ast, variable, &point, max_degree)
+    } else {
+        evaluator::evaluate_around(
+            &ast,
+            variable,
+            &point,
+            max_degree,
+            &FunctionRegistry::standard(),
+        )
+    }?;
+    if evaluator::is_coefficient_query(&ast) {
+        Ok(series.constant_term().to_string())
+    } else {
+        Ok(series.display_at(&point).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(max_degree: usize) -> Options {
+        Options {
+            max_degree,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn prints_polynomials_in_full_and_series_truncated() {
+        assert_eq!(run("(1+x)^2", &options(1)), Ok("1 + 2 x + x^2".to_string()));
+        assert_eq!(
+            run("1/(1-x)", &options(2)),
+            Ok("1 + x + x^2 + O(x^3)".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_errors_from_the_polynomial_path() {
+        let error = run("let g = x; g + foo", &options(2)).unwrap_err();
+        assert!(matches!(error, QueryError::Eval(_)));
+        assert_eq!(
+            error.render("let g = x; g + foo"),
+            "Undefined name: foo\n  |\n1 | let g = x; g + foo\n  |                ^^^"
+        );
+    }
+
+    #[test]
+    fn rejects_factor_with_modulus() {
+        let options = Options {
+            modulus: Some(7),
+            ..options(3)
+        };
+        assert_eq!(
+            run("factor(x^2 - 1)", &options),
+            Err(QueryError::FactorConflict("modular arithmetic"))
+        );
+    }
+}
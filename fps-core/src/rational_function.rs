@@ -0,0 +1,379 @@
+use num_bigint::{BigInt, BigUint};
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::fmt;
+
+use crate::evaluator::EvalError;
+use crate::factor::{gcd, lcm};
+use crate::polynomial::Polynomial;
+use crate::series::Series;
+
+/// An exact quotient of polynomials, kept in lowest terms.
+///
+/// The denominator has coprime integer coefficients and a positive lowest
+/// order coefficient, so `1/(1 - x - x^2)` is stored as written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RationalFunction {
+    numerator: Polynomial,
+    denominator: Polynomial,
+}
+
+impl RationalFunction {
+    /// `numerator / denominator` in lowest terms.
+    pub fn new(numerator: Polynomial, denominator: Polynomial) -> Result<Self, EvalError> {
+        if denominator.is_zero() {
+            return Err(EvalError::DivisionByZero);
+        }
+        if numerator.is_zero() {
+            let one = Polynomial::one().with_variable(denominator.variable_name());
+            return Ok(Self {
+                numerator,
+                denominator: one,
+            });
+        }
+
+        let common = numerator.gcd(&denominator);
+        let numerator = numerator.div_rem(&common)?.0;
+        let denominator = denominator.div_rem(&common)?.0;
+
+        let lowest = denominator
+            .coeffs()
+            .iter()
+            .find(|c| !c.is_zero())
+            .expect("denominator is non-zero");
+        let numerators = denominator
+            .coeffs()
+            .iter()
+            .fold(BigInt::zero(), |acc, c| gcd(&acc, c.numer()));
+        let denominators = denominator
+            .coeffs()
+            .iter()
+            .fold(BigInt::one(), |acc, c| lcm(&acc, c.denom()));
+        let mut scale = BigRational::new(denominators, numerators);
+        if lowest.is_negative() {
+            scale = -scale;
+        }
+
+        Ok(Self {
+            numerator: numerator.scale(&scale),
+            denominator: denominator.scale(&scale),
+        })
+    }
+
+    pub fn constant(value: BigRational) -> Self {
+        Self::from(Polynomial::constant(value))
+    }
+
+    /// The rational function `x`.
+    pub fn variable() -> Self {
+        Self::from(Polynomial::variable())
+    }
+
+    /// The same function, displayed in terms of `variable`.
+    pub fn with_variable(self, variable: char) -> Self {
+        Self {
+            numerator: self.numerator.with_variable(variable),
+            denominator: self.denominator.with_variable(variable),
+        }
+    }
+
+    pub fn numerator(&self) -> &Polynomial {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &Polynomial {
+        &self.denominator
+    }
+
+    /// The numerator, if the denominator is 1.
+    pub fn as_polynomial(&self) -> Option<&Polynomial> {
+        (self.denominator.is_constant()).then_some(&self.numerator)
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.numerator.is_constant() && self.denominator.is_constant()
+    }
+
+    pub fn constant_term(&self) -> BigRational {
+        self.coefficient(&BigInt::zero())
+    }
+
+    pub fn add(&self, other: &RationalFunction) -> RationalFunction {
+        let numerator = self
+            .numerator
+            .mul(&other.denominator)
+            .add(&other.numerator.mul(&self.denominator));
+        let denominator = self.denominator.mul(&other.denominator);
+        Self::new(numerator, denominator).expect("denominators are non-zero")
+    }
+
+    pub fn neg(&self) -> RationalFunction {
+        Self {
+            numerator: self.numerator.neg(),
+            denominator: self.denominator.clone(),
+        }
+    }
+
+    pub fn sub(&self, other: &RationalFunction) -> RationalFunction {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &RationalFunction) -> RationalFunction {
+        let numerator = self.numerator.mul(&other.numerator);
+        let denominator = self.denominator.mul(&other.denominator);
+        Self::new(numerator, denominator).expect("denominators are non-zero")
+    }
+
+    pub fn inverse(&self) -> Result<RationalFunction, EvalError> {
+        Self::new(self.denominator.clone(), self.numerator.clone())
+    }
+
+    pub fn div(&self, other: &RationalFunction) -> Result<RationalFunction, EvalError> {
+        Ok(self.mul(&other.inverse()?))
+    }
+
+    pub fn powi(&self, exponent: i64) -> Result<RationalFunction, EvalError> {
+        if exponent < 0 {
+            return self.inverse()?.powi(-exponent);
+        }
+        Ok(Self {
+            numerator: self.numerator.powi(exponent)?,
+            denominator: self.denominator.powi(exponent)?,
+        })
+    }
+
+    pub fn derivative(&self) -> RationalFunction {
+        // (p/q)' = (p' q - p q') / q^2
+        let numerator = self
+            .numerator
+            .derivative()
+            .mul(&self.denominator)
+            .sub(&self.numerator.mul(&self.denominator.derivative()));
+        let denominator = self.denominator.mul(&self.denominator);
+        Self::new(numerator, denominator).expect("denominator is non-zero")
+    }
+
+    /// `self(inner)`.
+    pub fn compose(&self, inner: &RationalFunction) -> Result<RationalFunction, EvalError> {
+        let horner = |p: &Polynomial| {
+            p.coeffs()
+                .iter()
+                .rev()
+                .fold(RationalFunction::from(Polynomial::zero()), |acc, c| {
+                    acc.mul(inner).add(&RationalFunction::constant(c.clone()))
+                })
+        };
+        horner(&self.numerator).div(&horner(&self.denominator))
+    }
+
+    /// Number of leading zero coefficients of the denominator, i.e. the
+    /// order of the pole at 0, and the denominator with them removed.
+    fn pole(&self) -> (usize, Polynomial) {
+        let coeffs = self.denominator.coeffs();
+        let order = coeffs.iter().position(|c| !c.is_zero()).unwrap_or(0);
+        let rest = Polynomial::new(coeffs[order..].to_vec());
+        (order, rest.with_variable(self.denominator.variable_name()))
+    }
+
+    /// Coefficient of `x^n` in the Laurent expansion around 0.
+    ///
+    /// Only the first `deg q` coefficients of the proper part `r/q` are
+    /// computed directly; later ones follow from the linear recurrence with
+    /// characteristic polynomial `x^deg(q) q(1/x)`, so even huge `n` are cheap.
+    pub fn coefficient(&self, n: &BigInt) -> BigRational {
+        let (pole, q) = self.pole();
+        let index = n + BigInt::from(pole);
+        if index.is_negative() {
+            return BigRational::zero();
+        }
+
+        let (quotient, remainder) = self.numerator.div_rem(&q).expect("q is non-zero");
+        let polynomial_part = index
+            .to_usize()
+            .map_or_else(BigRational::zero, |i| quotient.coefficient(i));
+
+        let degree = q.degree().unwrap_or(0);
+        if degree == 0 {
+            return polynomial_part;
+        }
+        let initial = series_coefficients(&remainder, &q, degree);
+        if let Some(i) = index.to_usize().filter(|&i| i < degree) {
+            return polynomial_part + &initial[i];
+        }
+
+        // x^index modulo the characteristic polynomial gives the weights of
+        // the initial terms.
+        let q0 = q.constant_term();
+        let characteristic = Polynomial::new(q.coeffs().iter().rev().map(|c| c / &q0).collect());
+        let exponent = index.to_biguint().expect("index is non-negative");
+        let weights = power_of_x_mod(&exponent, &characteristic);
+        let recurrent = (0..degree)
+            .map(|i| weights.coefficient(i) * &initial[i])
+            .fold(BigRational::zero(), |acc, term| acc + term);
+        polynomial_part + recurrent
+    }
+
+    /// Laurent expansion known up to `O(x^(max_degree+1))`.
+    pub fn to_series(&self, max_degree: usize) -> Series {
+        let (pole, q) = self.pole();
+        let degree = max_degree + pole;
+        let numerator = self.numerator.to_series(degree);
+        let denominator = q.to_series(degree);
+        numerator
+            .div(&denominator)
+            .expect("denominator has a non-zero constant term")
+            .shifted(-(pole as i64))
+    }
+}
+
+impl From<Polynomial> for RationalFunction {
+    fn from(polynomial: Polynomial) -> Self {
+        let one = Polynomial::one().with_variable(polynomial.variable_name());
+        Self {
+            numerator: polynomial,
+            denominator: one,
+        }
+    }
+}
+
+/// First `count` coefficients of `p/q`, where `q(0)` is non-zero.
+fn series_coefficients(p: &Polynomial, q: &Polynomial, count: usize) -> Vec<BigRational> {
+    let q0 = q.constant_term();
+    let mut coeffs: Vec<BigRational> = Vec::with_capacity(count);
+    for k in 0..count {
+        let sum = (1..=k.min(q.degree().unwrap_or(0)))
+            .map(|i| q.coefficient(i) * &coeffs[k - i])
+            .fold(p.coefficient(k), |acc, term| acc - term);
+        coeffs.push(sum / &q0);
+    }
+    coeffs
+}
+
+/// `x^exponent` modulo the monic polynomial `modulus`.
+fn power_of_x_mod(exponent: &BigUint, modulus: &Polynomial) -> Polynomial {
+    let reduce = |p: Polynomial| p.div_rem(modulus).expect("modulus is non-zero").1;
+    let mut result = reduce(Polynomial::one());
+    for i in (0..exponent.bits()).rev() {
+        result = reduce(result.mul(&result));
+        if exponent.bit(i) {
+            result = reduce(result.mul(&Polynomial::variable()));
+        }
+    }
+    result
+}
+
+impl fmt::Display for RationalFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denominator.is_constant() {
+            return write!(f, "{}", self.numerator);
+        }
+
+        let terms = |p: &Polynomial| p.coeffs().iter().filter(|c| !c.is_zero()).count();
+        if terms(&self.numerator) > 1 {
+            write!(f, "({})", self.numerator)?;
+        } else {
+            write!(f, "{}", self.numerator)?;
+        }
+        write!(f, "/")?;
+        // A lone power of x needs no parentheses; anything else does.
+        let lone_power =
+            terms(&self.denominator) == 1 && self.denominator.leading_coefficient().is_one();
+        if lone_power {
+            write!(f, "{}", self.denominator)
+        } else {
+            write!(f, "({})", self.denominator)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(coeffs: &[i64]) -> Polynomial {
+        Polynomial::new(
+            coeffs
+                .iter()
+                .map(|&c| BigRational::from_integer(c.into()))
+                .collect(),
+        )
+    }
+
+    fn fibonacci() -> RationalFunction {
+        RationalFunction::new(poly(&[1]), poly(&[1, -1, -1])).unwrap()
+    }
+
+    #[test]
+    fn reduces_to_lowest_terms() {
+        let f = RationalFunction::new(poly(&[-1, 0, 1]), poly(&[-2, 2])).unwrap();
+        let half = BigRational::new(1.into(), 2.into());
+        assert_eq!(f.as_polynomial(), Some(&poly(&[1, 1]).scale(&half)));
+
+        let g = RationalFunction::new(poly(&[0, 2]), poly(&[-4, 2])).unwrap();
+        assert_eq!(format!("{}", g), "-x/(2 - x)");
+        assert_eq!(format!("{}", fibonacci()), "1/(1 - x - x^2)");
+        assert_eq!(
+            RationalFunction::new(poly(&[1]), Polynomial::zero()),
+            Err(EvalError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn arithmetic_stays_exact() {
+        let x = RationalFunction::variable();
+        let one = RationalFunction::constant(BigRational::one());
+        let geometric = one.div(&one.sub(&x)).unwrap();
+        let sum = geometric.add(&geometric.powi(-1).unwrap());
+        assert_eq!(format!("{}", sum), "(2 - 2 x + x^2)/(1 - x)");
+        assert_eq!(format!("{}", geometric.derivative()), "1/(1 - 2 x + x^2)");
+        assert_eq!(
+            geometric.compose(&x.mul(&x)).unwrap(),
+            RationalFunction::new(poly(&[1]), poly(&[1, 0, -1])).unwrap()
+        );
+    }
+
+    #[test]
+    fn adds_high_powers_in_lowest_terms() {
+        let (p, q) = (poly(&[1, -1]).powi(64).unwrap(), poly(&[1, 2]).powi(64).unwrap());
+        let f = RationalFunction::new(poly(&[1]), p.clone()).unwrap();
+        let g = RationalFunction::new(poly(&[1]), q.clone()).unwrap();
+        assert_eq!(f.add(&g).denominator(), &p.mul(&q));
+        assert_eq!(f.add(&f).denominator(), &p);
+    }
+
+    #[test]
+    fn extracts_distant_coefficients() {
+        let f = fibonacci();
+        assert_eq!(
+            f.coefficient(&BigInt::from(10)),
+            BigRational::from_integer(89.into())
+        );
+        let n = BigInt::from(200);
+        let expected: BigInt = "453973694165307953197296969697410619233826"
+            .parse()
+            .unwrap();
+        assert_eq!(f.coefficient(&n), BigRational::from_integer(expected));
+
+        // x^-2 (1 + x^3) / (1 - x): a pole, a polynomial part and a tail.
+        let g = RationalFunction::new(poly(&[1, 0, 0, 1]), poly(&[0, 0, 1, -1])).unwrap();
+        assert_eq!(g.coefficient(&BigInt::from(-2)), BigRational::one());
+        assert_eq!(g.coefficient(&BigInt::from(-3)), BigRational::zero());
+        assert_eq!(
+            g.coefficient(&BigInt::from(5)),
+            BigRational::from_integer(2.into())
+        );
+    }
+
+    #[test]
+    fn converts_to_series_at_any_degree() {
+        assert_eq!(
+            format!("{}", fibonacci().to_series(5)),
+            "1 + x + 2 x^2 + 3 x^3 + 5 x^4 + 8 x^5 + O(x^6)"
+        );
+        let laurent = RationalFunction::new(poly(&[1]), poly(&[0, 0, 1, 1])).unwrap();
+        assert_eq!(
+            format!("{}", laurent.to_series(1)),
+            "x^-2 - x^-1 + 1 - x + O(x^2)"
+        );
+    }
+}
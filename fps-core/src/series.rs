@@ -1,146 +1,410 @@
 use num_bigint::BigInt;
-use num_rational::BigRational;
-use num_traits::{One, Signed, Zero};
+use num_rational::{BigRational, Rational64};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::borrow::Cow;
 use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
 
+use crate::coefficient;
+use crate::dense;
 use crate::evaluator::EvalError;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Series {
-    coeffs: Vec<BigRational>,
+/// A truncated Puiseux series `sum c_k x^(k/d) + O(x^(n/d))` with
+/// coefficients in `R`, exact rationals unless chosen otherwise.
+///
+/// The ramification `d` is a common denominator of all exponents; it is 1
+/// for power and Laurent series. Coefficients are stored from index
+/// `valuation` (the term `x^(valuation/d)`) up to the error term, so power
+/// series have valuation 0 and Laurent series a negative one.
+///
+/// Each series tracks its own precision, the order of its error term:
+/// operations that lose terms lower it, and combining two series keeps the
+/// lower of the two.
+///
+/// Ring operations are available for any [`coefficient::Coefficient`];
+/// division and the functions built on it need a [`coefficient::Field`].
+#[derive(Clone, Debug)]
+pub struct Series<R: coefficient::Coefficient = BigRational> {
+    coeffs: Vec<R>,
+    valuation: i64,
+    ramification: i64,
+    variable: char,
+    context: R::Context,
 }
 
+/// Name used for the series variable unless another one is chosen.
+pub const DEFAULT_VARIABLE: char = 'x';
+
 impl Series {
+    /// Zero, known up to `O(x^(max_degree+1))`.
     pub fn zero(max_degree: usize) -> Self {
-        Self {
-            coeffs: vec![BigRational::zero(); max_degree + 1],
-        }
+        Self::zero_in(max_degree, &())
     }
 
     pub fn one(max_degree: usize) -> Self {
-        let mut series = Self::zero(max_degree);
-        series.coeffs[0] = BigRational::one();
-        series
+        Self::one_in(max_degree, &())
     }
 
     pub fn constant(value: BigRational, max_degree: usize) -> Self {
+        Self::constant_in(value, max_degree, &())
+    }
+
+    pub fn variable(max_degree: usize) -> Self {
+        Self::variable_in(max_degree, &())
+    }
+
+    /// Power series `sum coeffs[k] x^k`, known up to `O(x^(max_degree+1))`.
+    /// Coefficients beyond `max_degree` are dropped.
+    pub fn from_coeffs(coeffs: &[BigRational], max_degree: usize) -> Self {
         let mut series = Self::zero(max_degree);
+        for (target, coeff) in series.coeffs.iter_mut().zip(coeffs) {
+            *target = coeff.clone();
+        }
+        series
+    }
+}
+
+impl<R: coefficient::Coefficient> Series<R> {
+    /// Zero with coefficients in the ring given by `context`, known up to
+    /// `O(x^(max_degree+1))`.
+    pub fn zero_in(max_degree: usize, context: &R::Context) -> Self {
+        Self {
+            coeffs: vec![R::zero(context); max_degree + 1],
+            valuation: 0,
+            ramification: 1,
+            variable: DEFAULT_VARIABLE,
+            context: context.clone(),
+        }
+    }
+
+    pub fn one_in(max_degree: usize, context: &R::Context) -> Self {
+        Self::constant_in(R::one(context), max_degree, context)
+    }
+
+    pub fn constant_in(value: R, max_degree: usize, context: &R::Context) -> Self {
+        let mut series = Self::zero_in(max_degree, context);
         series.coeffs[0] = value;
         series
     }
 
-    pub fn variable(max_degree: usize) -> Self {
-        let mut series = Self::zero(max_degree);
+    pub fn variable_in(max_degree: usize, context: &R::Context) -> Self {
+        let mut series = Self::zero_in(max_degree, context);
         if max_degree >= 1 {
-            series.coeffs[1] = BigRational::one();
+            series.coeffs[1] = R::one(context);
+        }
+        series
+    }
+
+    /// Series with the given coefficients and the ramification, variable
+    /// and ring of `self`.
+    fn with_coeffs(&self, valuation: i64, coeffs: Vec<R>) -> Series<R> {
+        Series {
+            coeffs,
+            valuation,
+            ramification: self.ramification,
+            variable: self.variable,
+            context: self.context.clone(),
+        }
+    }
+
+    /// Series with the valuation, precision and variable of `self`, but the
+    /// given coefficients from another ring.
+    pub(crate) fn with_ring<S: coefficient::Coefficient>(
+        &self,
+        coeffs: Vec<S>,
+        context: S::Context,
+    ) -> Series<S> {
+        debug_assert_eq!(coeffs.len(), self.coeffs.len());
+        Series {
+            coeffs,
+            valuation: self.valuation,
+            ramification: self.ramification,
+            variable: self.variable,
+            context,
+        }
+    }
+
+    /// Renames the series variable used when displaying the series.
+    pub fn with_variable(mut self, variable: char) -> Self {
+        self.variable = variable;
+        self
+    }
+
+    pub fn variable_name(&self) -> char {
+        self.variable
+    }
+
+    /// The ring the coefficients belong to, such as a modulus.
+    pub fn context(&self) -> &R::Context {
+        &self.context
+    }
+
+    fn zero_coeff(&self) -> R {
+        R::zero(&self.context)
+    }
+
+    /// Zero series with the same valuation, precision and variable as `self`.
+    fn zero_like(&self) -> Series<R> {
+        self.with_coeffs(self.valuation, vec![self.zero_coeff(); self.coeffs.len()])
+    }
+
+    /// One, known to as many terms as `self`.
+    fn one_like(&self) -> Series<R> {
+        let mut series = self.with_coeffs(0, vec![self.zero_coeff(); self.coeffs.len()]);
+        if let Some(c0) = series.coeffs.first_mut() {
+            *c0 = R::one(&self.context);
         }
         series
     }
 
-    pub fn coeffs(&self) -> &[BigRational] {
+    /// Known coefficients, starting with that of `x^valuation`.
+    pub fn coeffs(&self) -> &[R] {
         &self.coeffs
     }
 
-    pub fn max_degree(&self) -> usize {
-        self.coeffs.len().saturating_sub(1)
+    /// Exponent of the first stored coefficient.
+    pub fn valuation(&self) -> Rational64 {
+        Rational64::new(self.valuation, self.ramification)
     }
 
-    pub fn constant_term(&self) -> BigRational {
-        self.coeffs
-            .get(0)
-            .cloned()
-            .unwrap_or_else(BigRational::zero)
+    /// Common denominator of the exponents.
+    pub fn ramification(&self) -> i64 {
+        self.ramification
     }
 
-    pub fn is_constant(&self) -> bool {
-        self.coeffs.iter().skip(1).all(|c| c.is_zero())
+    /// Exponent of the `O(x^n)` error term: coefficients below it are exact.
+    pub fn order(&self) -> Rational64 {
+        Rational64::new(self.order_index(), self.ramification)
+    }
+
+    /// Index of the error term, in units of `1/ramification`.
+    fn order_index(&self) -> i64 {
+        self.valuation + self.coeffs.len() as i64
     }
 
-    pub fn add(&self, other: &Series) -> Series {
-        assert_eq!(self.coeffs.len(), other.coeffs.len());
-        let mut result = self.clone();
-        for (r, o) in result.coeffs.iter_mut().zip(other.coeffs.iter()) {
-            *r += o;
+    /// Coefficient of `x^exponent`, or `None` if it lies beyond the known
+    /// terms.
+    pub fn coefficient(&self, exponent: Rational64) -> Option<R> {
+        let index = exponent * self.ramification;
+        if index >= Rational64::from_integer(self.order_index()) {
+            None
+        } else if !index.is_integer() {
+            Some(self.zero_coeff())
+        } else {
+            Some(self.coeff_at(index.to_integer()))
         }
-        result
     }
 
-    pub fn neg(&self) -> Series {
-        let mut result = self.clone();
-        for coeff in &mut result.coeffs {
-            *coeff = -coeff.clone();
+    /// Coefficient at index `n`, i.e. of `x^(n/ramification)`, for `n`
+    /// below the order.
+    fn coeff_at(&self, n: i64) -> R {
+        if n < self.valuation {
+            self.zero_coeff()
+        } else {
+            self.coeffs[(n - self.valuation) as usize].clone()
         }
-        result
     }
 
-    pub fn sub(&self, other: &Series) -> Series {
-        self.add(&other.neg())
+    /// Number of stored coefficients that are zero before the first
+    /// non-zero one.
+    fn leading_zeros(&self) -> usize {
+        self.coeffs
+            .iter()
+            .position(|c| !c.is_zero())
+            .unwrap_or(self.coeffs.len())
     }
 
-    pub fn mul(&self, other: &Series) -> Series {
-        assert_eq!(self.coeffs.len(), other.coeffs.len());
-        let max_degree = self.max_degree();
-        let mut result = Series::zero(max_degree);
+    /// The same series without stored zeros before the first non-zero
+    /// coefficient.
+    pub(crate) fn trimmed(mut self) -> Series<R> {
+        let zeros = self.leading_zeros();
+        self.coeffs.drain(..zeros);
+        self.valuation += zeros as i64;
+        self
+    }
 
-        for i in 0..=max_degree {
-            for j in 0..=max_degree {
-                if i + j > max_degree {
-                    break;
-                }
-                result.coeffs[i + j] += self.coeffs[i].clone() * other.coeffs[j].clone();
-            }
+    /// `self` times `x^k`.
+    pub(crate) fn shifted(mut self, k: i64) -> Series<R> {
+        self.valuation += k * self.ramification;
+        self
+    }
+
+    /// Drops all terms of degree `order` and above.
+    pub fn truncated(mut self, order: i64) -> Series<R> {
+        let order = order * self.ramification;
+        if order <= self.valuation {
+            self.coeffs.clear();
+            self.valuation = order;
+        } else {
+            self.coeffs.truncate((order - self.valuation) as usize);
         }
+        self
+    }
 
-        result
+    /// The same series with valuation 0, for algorithms that only make
+    /// sense on power series. Fails if there are negative powers.
+    fn power_series(&self, function: &'static str) -> Result<Series<R>, EvalError> {
+        let zeros = self.leading_zeros();
+        let valuation = self.valuation + zeros as i64;
+        if valuation < 0 {
+            return Err(EvalError::NegativePowers(function));
+        }
+
+        let mut coeffs = vec![self.zero_coeff(); valuation as usize];
+        coeffs.extend_from_slice(&self.coeffs[zeros..]);
+        Ok(self.with_coeffs(0, coeffs))
     }
 
-    pub fn scale(&self, scalar: &BigRational) -> Series {
-        let mut result = self.clone();
-        for coeff in &mut result.coeffs {
-            *coeff *= scalar.clone();
+    /// The same series over ramification `d`, a multiple of the current one.
+    fn ramified(&self, d: i64) -> Cow<'_, Series<R>> {
+        if d == self.ramification {
+            return Cow::Borrowed(self);
         }
-        result
+
+        let m = (d / self.ramification) as usize;
+        let mut coeffs = vec![self.zero_coeff(); self.coeffs.len() * m];
+        for (i, coeff) in self.coeffs.iter().enumerate() {
+            coeffs[i * m] = coeff.clone();
+        }
+        Cow::Owned(Series {
+            coeffs,
+            valuation: self.valuation * m as i64,
+            ramification: d,
+            variable: self.variable,
+            context: self.context.clone(),
+        })
     }
 
-    pub fn inverse(&self) -> Result<Series, EvalError> {
-        let max_degree = self.max_degree();
-        let a0 = self.constant_term();
+    /// Fails with [`EvalError::ExponentTooLarge`] if `self` and `other` have
+    /// no common ramification up to `MAX_RAMIFICATION`, so that adding or
+    /// multiplying them would store too many coefficients.
+    pub(crate) fn check_aligned(&self, other: &Series<R>) -> Result<(), EvalError> {
+        let d = self.ramification / gcd(self.ramification, other.ramification);
+        check_ramification(d.saturating_mul(other.ramification))
+    }
 
-        if a0.is_zero() {
-            return Err(EvalError::DivisionByZero);
+    /// Both series over their least common ramification.
+    fn aligned<'a>(&'a self, other: &'a Series<R>) -> (Cow<'a, Series<R>>, Cow<'a, Series<R>>) {
+        let d = self.ramification / gcd(self.ramification, other.ramification) * other.ramification;
+        (self.ramified(d), other.ramified(d))
+    }
+
+    /// The same series with integer exponents, for operations that need
+    /// them. Terms beyond the last whole power of `x` are dropped.
+    fn unramified(&self, function: &'static str) -> Result<Cow<'_, Series<R>>, EvalError> {
+        let d = self.ramification;
+        if d == 1 {
+            return Ok(Cow::Borrowed(self));
         }
 
-        let mut result = Series::zero(max_degree);
-        result.coeffs[0] = BigRational::one() / a0.clone();
+        let fractional = (self.valuation..)
+            .zip(&self.coeffs)
+            .any(|(k, coeff)| k % d != 0 && !coeff.is_zero());
+        if fractional {
+            return Err(EvalError::FractionalExponents(function));
+        }
 
-        for n in 1..=max_degree {
-            let mut sum = BigRational::zero();
-            for k in 1..=n {
-                sum += self.coeffs[k].clone() * result.coeffs[n - k].clone();
-            }
-            result.coeffs[n] = -sum / a0.clone();
+        let valuation = self.valuation.div_euclid(d) + i64::from(self.valuation % d != 0);
+        let order = self.order_index().div_euclid(d).max(valuation);
+        let coeffs = (valuation..order).map(|k| self.coeff_at(k * d)).collect();
+        Ok(Cow::Owned(Series {
+            coeffs,
+            valuation,
+            ramification: 1,
+            variable: self.variable,
+            context: self.context.clone(),
+        }))
+    }
+
+    /// Length of the dense coefficient vector minus one; only meaningful
+    /// for power series.
+    fn max_degree(&self) -> usize {
+        self.coeffs.len().saturating_sub(1)
+    }
+
+    pub fn constant_term(&self) -> R {
+        self.coefficient(Rational64::from_integer(0))
+            .unwrap_or_else(|| self.zero_coeff())
+    }
+
+    pub fn is_constant(&self) -> bool {
+        (self.valuation..self.order_index()).all(|k| k == 0 || self.coeff_at(k).is_zero())
+    }
+
+    /// Sum of both series, known up to the lower of the two orders.
+    pub fn add(&self, other: &Series<R>) -> Series<R> {
+        let (a, b) = self.aligned(other);
+        let order = a.order_index().min(b.order_index());
+        let valuation = a.valuation.min(b.valuation).min(order);
+        let coeffs = (valuation..order)
+            .map(|k| a.coeff_at(k).add(&b.coeff_at(k)))
+            .collect();
+        a.with_coeffs(valuation, coeffs)
+    }
+
+    pub fn neg(&self) -> Series<R> {
+        self.with_coeffs(self.valuation, self.coeffs.iter().map(R::neg).collect())
+    }
+
+    pub fn sub(&self, other: &Series<R>) -> Series<R> {
+        self.add(&other.neg())
+    }
+
+    /// Product of both series. Leading zeros are skipped, so the result is
+    /// known to as many terms as the less precise factor after its first
+    /// non-zero coefficient.
+    pub fn mul(&self, other: &Series<R>) -> Series<R> {
+        let (lhs, rhs) = self.aligned(other);
+        let (zeros_a, zeros_b) = (lhs.leading_zeros(), rhs.leading_zeros());
+        let a = &lhs.coeffs[zeros_a..];
+        let b = &rhs.coeffs[zeros_b..];
+        let coeffs = R::mul_truncated(a, b, a.len().min(b.len()), &self.context);
+
+        let valuation = lhs.valuation + zeros_a as i64 + rhs.valuation + zeros_b as i64;
+        lhs.with_coeffs(valuation, coeffs)
+    }
+
+    pub fn scale(&self, scalar: &R) -> Series<R> {
+        let coeffs = self.coeffs.iter().map(|c| c.mul(scalar)).collect();
+        self.with_coeffs(self.valuation, coeffs)
+    }
+}
+
+impl<R: coefficient::Field> Series<R> {
+    /// `1/self`. A leading `x^v` factor becomes `x^-v`, so this only fails
+    /// if no coefficient is known to be non-zero.
+    pub fn inverse(&self) -> Result<Series<R>, EvalError> {
+        let zeros = self.leading_zeros();
+        let coeffs = &self.coeffs[zeros..];
+        if coeffs.is_empty() {
+            return Err(EvalError::DivisionByZero);
         }
 
-        Ok(result)
+        let result = dense::inverse(coeffs, coeffs.len(), &self.context)?;
+        let valuation = -(self.valuation + zeros as i64);
+        Ok(self.with_coeffs(valuation, result))
     }
 
-    pub fn div(&self, other: &Series) -> Result<Series, EvalError> {
+    /// `self / other`. Common powers of `x` cancel, so `sin(x)/x` is a power
+    /// series, known to as many terms as the shorter operand after its
+    /// leading zeros.
+    pub fn div(&self, other: &Series<R>) -> Result<Series<R>, EvalError> {
+        self.check_aligned(other)?;
         let inverse = other.inverse()?;
         Ok(self.mul(&inverse))
     }
 
-    pub fn powi(&self, exponent: i64) -> Result<Series, EvalError> {
-        let max_degree = self.max_degree();
-
+    pub fn powi(&self, exponent: i64) -> Result<Series<R>, EvalError> {
         if exponent == 0 {
-            return Ok(Series::one(max_degree));
+            return Ok(self.one_like());
         }
 
         if exponent < 0 {
             return self.inverse()?.powi(-exponent);
         }
 
-        let mut result = Series::one(max_degree);
+        let mut result = self.one_like();
         let mut base = self.clone();
         let mut exp = exponent;
 
@@ -157,186 +421,442 @@ impl Series {
         Ok(result)
     }
 
-    pub fn sin(&self) -> Result<Series, EvalError> {
-        if !self.constant_term().is_zero() {
-            return Err(EvalError::FunctionRequiresZeroConstant("sin"));
+    /// Computes `self(inner(x))`. The inner series must have zero constant
+    /// term and no negative powers so every coefficient of the result is a
+    /// finite sum. Negative powers in `self` become powers of `1/inner`;
+    /// fractional ones are not supported.
+    pub fn compose(&self, inner: &Series<R>) -> Result<Series<R>, EvalError> {
+        let outer = self.unramified("compose")?;
+        let inner_valuation = inner.valuation + inner.leading_zeros() as i64;
+        if inner_valuation < 0 {
+            return Err(EvalError::NegativePowers("compose"));
         }
-
-        let max_degree = self.max_degree();
-        let mut result = Series::zero(max_degree);
-
-        if max_degree == 0 {
-            return Ok(result);
+        if inner_valuation == 0 {
+            return Err(EvalError::FunctionRequiresZeroConstant("compose"));
         }
 
-        let mut factorial = BigInt::one();
-        for n in 0..=((max_degree - 1) / 2) {
-            if n > 0 {
-                let two_n = 2 * (n as i64);
-                factorial *= BigInt::from(two_n);
-                factorial *= BigInt::from(two_n + 1);
-            }
+        // self = x^v p(x) with p a power series, so self(inner) = inner^v p(inner).
+        let zeros = outer.leading_zeros();
+        let valuation = outer.valuation + zeros as i64;
+        let p = outer.with_coeffs(0, outer.coeffs[zeros..].to_vec());
 
-            let power = self.powi((2 * n + 1) as i64)?;
-            let sign = if n % 2 == 0 {
-                BigInt::one()
-            } else {
-                -BigInt::one()
-            };
-            let coeff = BigRational::new(sign, factorial.clone());
-            result = result.add(&power.scale(&coeff));
+        // Horner's scheme: c_0 + inner (c_1 + inner (c_2 + ...)).
+        let mut result = p.zero_like();
+        for coeff in p.coeffs.iter().rev() {
+            result = result.mul(inner).add(&p.one_like().scale(coeff));
         }
 
-        Ok(result)
+        if valuation == 0 {
+            return Ok(result);
+        }
+        Ok(result.mul(&inner.powi(valuation)?))
     }
 
-    pub fn cos(&self) -> Result<Series, EvalError> {
-        if !self.constant_term().is_zero() {
-            return Err(EvalError::FunctionRequiresZeroConstant("cos"));
+    /// Compositional inverse: the series `g` with `self(g(x)) = x`.
+    ///
+    /// Uses Lagrange inversion. Writing `self = x h(x)`, the coefficients are
+    /// `[x^n] g = (1/n) [x^(n-1)] h^(-n)`.
+    pub fn reversion(&self) -> Result<Series<R>, EvalError> {
+        let series = self
+            .unramified("revert")
+            .and_then(|series| series.power_series("revert"))
+            .map_err(|_| EvalError::NotReversible)?;
+        let max_degree = series.max_degree();
+        let linear = series
+            .coeffs
+            .get(1)
+            .cloned()
+            .unwrap_or_else(|| self.zero_coeff());
+        if !series.constant_term().is_zero() || (max_degree > 0 && linear.is_zero()) {
+            return Err(EvalError::NotReversible);
         }
 
-        let max_degree = self.max_degree();
-        let mut result = Series::zero(max_degree);
-
+        let mut result = series.zero_like();
         if max_degree == 0 {
-            result.coeffs[0] = BigRational::one();
             return Ok(result);
         }
 
-        let mut factorial = BigInt::one();
-        for n in 0..=(max_degree / 2) {
-            if n > 0 {
-                let two_n = 2 * (n as i64);
-                factorial *= BigInt::from(two_n - 1);
-                factorial *= BigInt::from(two_n);
-            }
+        let mut h = series.zero_like();
+        h.coeffs[..max_degree].clone_from_slice(&series.coeffs[1..]);
+        let h_inverse = h.inverse()?;
 
-            let power = if n == 0 {
-                Series::one(max_degree)
-            } else {
-                self.powi((2 * n) as i64)?
-            };
-            let sign = if n % 2 == 0 {
-                BigInt::one()
-            } else {
-                -BigInt::one()
-            };
-            let coeff = BigRational::new(sign, factorial.clone());
-            result = result.add(&power.scale(&coeff));
+        let mut power = series.one_like();
+        for n in 1..=max_degree {
+            power = power.mul(&h_inverse);
+            let inverse_n = R::reciprocal(n as i64, &self.context)?;
+            result.coeffs[n] = power.coeff_at(n as i64 - 1).mul(&inverse_n);
         }
 
         Ok(result)
     }
 
-    pub fn exp(&self) -> Result<Series, EvalError> {
-        if !self.constant_term().is_zero() {
-            return Err(EvalError::FunctionRequiresZeroConstant("exp"));
-        }
-
-        let max_degree = self.max_degree();
-        let mut result = Series::zero(max_degree);
-        let mut factorial = BigInt::one();
+    /// Term-by-term derivative. The result is one order less precise, since
+    /// the unknown `x^n` term of `self` contributes to `x^(n-1)`.
+    pub fn derivative(&self) -> Result<Series<R>, EvalError> {
+        let d = self.ramification;
+        // The constant term vanishes; skip it rather than storing an x^-1 term.
+        let skip = usize::from(self.valuation == 0 && !self.coeffs.is_empty());
+        let first = self.valuation + skip as i64;
+        let inverse_d = R::reciprocal(d, &self.context)?;
+        let coeffs = self.coeffs[skip..]
+            .iter()
+            .zip(first..)
+            .map(|(coeff, k)| {
+                let k = R::from_integer(&BigInt::from(k), &self.context);
+                coeff.mul(&k).mul(&inverse_d)
+            })
+            .collect();
+        Ok(self.with_coeffs(first - d, coeffs))
+    }
 
-        for n in 0..=max_degree {
-            if n > 0 {
-                factorial *= BigInt::from(n as i64);
-            }
-            let power = self.powi(n as i64)?;
-            let coeff = BigRational::new(BigInt::one(), factorial.clone());
-            result = result.add(&power.scale(&coeff));
+    /// Term-by-term antiderivative with the given constant term. The result
+    /// is one order more precise than `self`. Fails if `self` has an `x^-1`
+    /// term, whose antiderivative is a logarithm, or if a term's new
+    /// exponent is zero in the coefficient field.
+    pub fn integral(&self, constant: &R) -> Result<Series<R>, EvalError> {
+        if self
+            .coefficient(Rational64::from_integer(-1))
+            .is_some_and(|c| !c.is_zero())
+        {
+            return Err(EvalError::LogarithmicIntegral);
         }
 
+        // x^(k/d) integrates to d/(k+d) x^((k+d)/d).
+        let d = self.ramification;
+        let d_coeff = R::from_integer(&BigInt::from(d), &self.context);
+        let order = self.order_index() + d;
+        let valuation = (self.valuation + d).min(0).min(order);
+        let coeffs = (valuation..order)
+            .map(|k| {
+                let coeff = self.coeff_at(k - d);
+                if k == 0 {
+                    Ok(constant.clone())
+                } else if coeff.is_zero() {
+                    Ok(coeff)
+                } else {
+                    Ok(coeff.mul(&d_coeff).mul(&R::reciprocal(k, &self.context)?))
+                }
+            })
+            .collect::<Result<_, EvalError>>()?;
+        Ok(self.with_coeffs(valuation, coeffs))
+    }
+
+    /// Power with a rational exponent, `a0^e (self/a0)^e` with `a0` the
+    /// first non-zero coefficient. The constant term `a0` must have an
+    /// `e`-th power in the coefficient field, see
+    /// [`coefficient::Field::powr`]. A leading `x^v` factor becomes
+    /// `x^(v e)`, which may need a larger ramification, up to
+    /// `MAX_RAMIFICATION`.
+    pub fn powr(&self, exponent: &BigRational) -> Result<Series<R>, EvalError> {
+        let zeros = self.leading_zeros();
+        let Some(a0) = self.coeffs.get(zeros) else {
+            return Err(EvalError::NonIntegerPowerOfZero);
+        };
+        let shift = BigRational::new(
+            BigInt::from(self.valuation + zeros as i64),
+            BigInt::from(self.ramification),
+        ) * exponent;
+        let (Some(shift_numer), Some(shift_denom)) =
+            (shift.numer().to_i64(), shift.denom().to_i64())
+        else {
+            return Err(EvalError::ExponentTooLarge);
+        };
+        let d = self.ramification / gcd(self.ramification, shift_denom);
+        let d = d.saturating_mul(shift_denom);
+        check_ramification(d)?;
+        let leading = a0.powr(exponent)?;
+
+        let unit = &self.coeffs[zeros..];
+        let power = dense::pow(unit, exponent, unit.len(), &self.context)?;
+        let result = self.with_coeffs(0, power);
+
+        // Multiply by x^shift, over a ramification that can express it.
+        let mut result = result.scale(&leading).ramified(d).into_owned();
+        result.valuation += shift_numer * (d / shift_denom);
         Ok(result)
     }
 
-    pub fn log(&self) -> Result<Series, EvalError> {
-        if self.constant_term() != BigRational::one() {
-            return Err(EvalError::LogRequiresUnitConstant);
+    fn sin_cos(&self, function: &'static str) -> Result<(Series<R>, Series<R>), EvalError> {
+        let series = self.power_series(function)?;
+        if !series.constant_term().is_zero() {
+            return Err(EvalError::FunctionRequiresZeroConstant(function));
         }
 
-        let max_degree = self.max_degree();
-        let mut result = Series::zero(max_degree);
+        let (sin, cos) = dense::sin_cos(&series.coeffs, series.coeffs.len(), &self.context)?;
+        Ok((series.with_coeffs(0, sin), series.with_coeffs(0, cos)))
+    }
 
-        if max_degree == 0 {
-            return Ok(result);
+    pub fn sin(&self) -> Result<Series<R>, EvalError> {
+        Ok(self.sin_cos("sin")?.0)
+    }
+
+    pub fn cos(&self) -> Result<Series<R>, EvalError> {
+        Ok(self.sin_cos("cos")?.1)
+    }
+
+    pub fn exp(&self) -> Result<Series<R>, EvalError> {
+        let series = self.power_series("exp")?;
+        if !series.constant_term().is_zero() {
+            return Err(EvalError::FunctionRequiresZeroConstant("exp"));
         }
 
-        let adjustment = self.sub(&Series::one(max_degree));
+        let coeffs = dense::exp(&series.coeffs, series.coeffs.len(), &self.context)?;
+        Ok(series.with_coeffs(0, coeffs))
+    }
 
-        for n in 1..=max_degree {
-            let power = adjustment.powi(n as i64)?;
-            let sign = if n % 2 == 1 {
-                BigInt::one()
-            } else {
-                -BigInt::one()
-            };
-            let coeff = BigRational::new(sign, BigInt::from(n as i64));
-            result = result.add(&power.scale(&coeff));
+    pub fn log(&self) -> Result<Series<R>, EvalError> {
+        let series = self.power_series("log")?;
+        if !series.constant_term().is_one() {
+            return Err(EvalError::LogRequiresUnitConstant);
         }
 
-        Ok(result)
+        let coeffs = dense::log(&series.coeffs, series.coeffs.len(), &self.context)?;
+        Ok(series.with_coeffs(0, coeffs))
     }
 }
 
-impl fmt::Display for Series {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut first = true;
+impl<R: coefficient::Coefficient> PartialEq for Series<R> {
+    /// Series are equal when they have the same variable and precision and
+    /// agree on every known coefficient, however they are stored.
+    fn eq(&self, other: &Series<R>) -> bool {
+        let (a, b) = self.aligned(other);
+        a.variable == b.variable
+            && a.order_index() == b.order_index()
+            && (a.valuation.min(b.valuation)..a.order_index())
+                .all(|k| a.coeff_at(k) == b.coeff_at(k))
+    }
+}
 
-        for (degree, coeff) in self.coeffs.iter().enumerate() {
-            if coeff.is_zero() {
-                continue;
-            }
+impl<R: coefficient::Coefficient + Eq> Eq for Series<R> {}
 
-            let is_negative = coeff.is_negative();
-            let abs_coeff = if is_negative {
-                -coeff.clone()
-            } else {
-                coeff.clone()
-            };
+/// Point a series is expanded around: `x = a + t` for a finite point, or
+/// `x = 1/t` at infinity. The series itself is always in `t`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpansionPoint {
+    Finite(BigRational),
+    Infinity,
+}
 
-            if first {
-                if is_negative {
-                    write!(f, "-{}", format_term(&abs_coeff, degree))?;
-                } else {
-                    write!(f, "{}", format_term(&abs_coeff, degree))?;
-                }
-                first = false;
-            } else if is_negative {
-                write!(f, " - {}", format_term(&abs_coeff, degree))?;
-            } else {
-                write!(f, " + {}", format_term(&abs_coeff, degree))?;
-            }
+impl Default for ExpansionPoint {
+    fn default() -> Self {
+        ExpansionPoint::Finite(BigRational::zero())
+    }
+}
+
+impl FromStr for ExpansionPoint {
+    type Err = InvalidExpansionPoint;
+
+    /// Parses `inf`, `infinity` or `oo`, or a rational such as `-3/2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "inf" | "infinity" | "oo" => Ok(ExpansionPoint::Infinity),
+            point => point
+                .parse()
+                .map(ExpansionPoint::Finite)
+                .map_err(|_| InvalidExpansionPoint(s.to_string())),
         }
+    }
+}
 
-        if first {
-            write!(f, "0")?;
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Invalid expansion point {0}: expected a rational number or inf")]
+pub struct InvalidExpansionPoint(String);
+
+/// Displays a series as an expansion around a point, see [`Series::display_at`].
+pub struct DisplayAt<'a> {
+    series: &'a Series,
+    point: &'a ExpansionPoint,
+}
+
+impl Series {
+    /// Displays `self`, a series in `t`, in terms of `x` near `point`:
+    /// powers of `t` become powers of `(x-a)`, or of `1/x` at infinity.
+    pub fn display_at<'a>(&'a self, point: &'a ExpansionPoint) -> DisplayAt<'a> {
+        DisplayAt {
+            series: self,
+            point,
         }
+    }
+}
 
-        write!(f, " + O(x^{})", self.max_degree() + 1)
+impl<R: coefficient::Coefficient> fmt::Display for Series<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let base = self.variable.to_string();
+        write_series(f, self, &base, false, |coeff, exponent| {
+            format_term(coeff, exponent, &base)
+        })
     }
 }
 
-fn format_term(coeff: &BigRational, degree: usize) -> String {
-    match degree {
-        0 => format_rational(coeff),
-        1 => {
-            if coeff.is_one() {
-                "x".to_string()
+impl fmt::Display for DisplayAt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let series = self.series;
+        let base = match self.point {
+            ExpansionPoint::Finite(a) if a.is_zero() => series.variable.to_string(),
+            ExpansionPoint::Finite(a) if a.is_negative() => {
+                format!("({}+{})", series.variable, format_rational(&-a))
+            }
+            ExpansionPoint::Finite(a) => format!("({}-{})", series.variable, format_rational(a)),
+            ExpansionPoint::Infinity => series.variable.to_string(),
+        };
+        let at_infinity = *self.point == ExpansionPoint::Infinity;
+        write_series(f, series, &base, at_infinity, |coeff, exponent| {
+            if at_infinity {
+                format_inverse_term(coeff, exponent, &base)
             } else {
-                format!("{} x", format_rational(coeff))
+                format_term(coeff, exponent, &base)
             }
+        })
+    }
+}
+
+/// Writes the non-zero terms of `series`, each formatted by `term` from
+/// the absolute value of its coefficient and its exponent, followed by the
+/// error term in powers of `base`, or of `1/base` at infinity.
+fn write_series<R: coefficient::Coefficient>(
+    f: &mut fmt::Formatter<'_>,
+    series: &Series<R>,
+    base: &str,
+    at_infinity: bool,
+    term: impl Fn(&R, Rational64) -> String,
+) -> fmt::Result {
+    let mut first = true;
+    for (coeff, index) in series.coeffs.iter().zip(series.valuation..) {
+        if coeff.is_zero() {
+            continue;
         }
-        _ => {
-            if coeff.is_one() {
-                format!("x^{}", degree)
+
+        let is_negative = coeff.is_negative();
+        let abs_coeff = if is_negative {
+            coeff.neg()
+        } else {
+            coeff.clone()
+        };
+        let term = term(&abs_coeff, Rational64::new(index, series.ramification));
+
+        if first {
+            if is_negative {
+                write!(f, "-{}", term)?;
             } else {
-                format!("{} x^{}", format_rational(coeff), degree)
+                write!(f, "{}", term)?;
             }
+            first = false;
+        } else if is_negative {
+            write!(f, " - {}", term)?;
+        } else {
+            write!(f, " + {}", term)?;
+        }
+    }
+
+    let order = series.order();
+    if !first {
+        write!(f, " + ")?;
+    } else if !order.is_zero() {
+        write!(f, "0 + ")?;
+    }
+
+    let power = |exponent: Rational64| {
+        if exponent.is_integer() {
+            format!("{}^{}", base, exponent)
+        } else {
+            format!("{}^({})", base, exponent)
         }
+    };
+    if order.is_zero() {
+        write!(f, "O(1)")
+    } else if !at_infinity {
+        write!(f, "O({})", power(order))
+    } else if order.is_positive() {
+        write!(f, "O(1/{})", power(order))
+    } else {
+        write!(f, "O({})", power(-order))
+    }
+}
+
+pub(crate) fn format_term<R: coefficient::Coefficient>(
+    coeff: &R,
+    exponent: Rational64,
+    base: &str,
+) -> String {
+    if exponent.is_zero() {
+        coeff.to_string()
+    } else if coeff.is_one() {
+        format_power(base, exponent)
+    } else {
+        format!("{} {}", coeff, format_power(base, exponent))
+    }
+}
+
+/// Term `coeff t^exponent` with `t = 1/x`, e.g. `3/x^2` or `1/(6 x)`.
+fn format_inverse_term(coeff: &BigRational, exponent: Rational64, base: &str) -> String {
+    if !exponent.is_positive() {
+        return format_term(coeff, -exponent, base);
+    }
+
+    let power = format_power(base, exponent);
+    if coeff.is_integer() {
+        format!("{}/{}", coeff.numer(), power)
+    } else {
+        format!("{}/({} {})", coeff.numer(), coeff.denom(), power)
+    }
+}
+
+/// `x`, `x^3`, `x^-2` or `x^(1/2)`.
+fn format_power(base: &str, exponent: Rational64) -> String {
+    if exponent.is_one() {
+        base.to_string()
+    } else if exponent.is_integer() {
+        format!("{}^{}", base, exponent)
+    } else {
+        format!("{}^({})", base, exponent)
     }
 }
 
-fn format_rational(rational: &BigRational) -> String {
+/// Largest ramification a series may have. A series over ramification `d`
+/// stores `d` coefficients per power of `x`, most of them zero.
+const MAX_RAMIFICATION: i64 = 1000;
+
+fn check_ramification(d: i64) -> Result<(), EvalError> {
+    if d > MAX_RAMIFICATION {
+        return Err(EvalError::ExponentTooLarge);
+    }
+    Ok(())
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+/// Computes `base^exponent` exactly, failing if the result is irrational.
+pub(crate) fn rational_power(
+    base: &BigRational,
+    exponent: &BigRational,
+) -> Result<BigRational, EvalError> {
+    let irrational =
+        || EvalError::IrrationalPower(format_rational(base), format_rational(exponent));
+    let root_degree = exponent
+        .denom()
+        .to_u32()
+        .ok_or(EvalError::ExponentTooLarge)?;
+    let power = exponent
+        .numer()
+        .to_i32()
+        .ok_or(EvalError::ExponentTooLarge)?;
+    if base.is_negative() && root_degree % 2 == 0 {
+        return Err(irrational());
+    }
+
+    let exact_root = |n: &BigInt| {
+        let root = n.nth_root(root_degree);
+        (root.pow(root_degree) == *n).then_some(root)
+    };
+    let numer = exact_root(base.numer()).ok_or_else(irrational)?;
+    let denom = exact_root(base.denom()).ok_or_else(irrational)?;
+
+    Ok(BigRational::new(numer, denom).pow(power))
+}
+
+pub(crate) fn format_rational(rational: &BigRational) -> String {
     if rational.is_integer() {
         rational.to_integer().to_string()
     } else {
@@ -367,6 +887,13 @@ mod tests {
         assert_eq!(format!("{}", series), "1 + x + x^2 + x^3 + O(x^4)");
     }
 
+    #[test]
+    fn display_uses_series_variable() {
+        let series = series_from_coeffs(&[0, 2, -1], 2).with_variable('q');
+        assert_eq!(format!("{}", series.mul(&series)), "4 q^2 - 4 q^3 + O(q^4)");
+        assert_eq!(format!("{}", series), "2 q - q^2 + O(q^3)");
+    }
+
     #[test]
     fn multiplication_truncates_to_degree() {
         let a = series_from_coeffs(&[1, 1], 3); // 1 + x
@@ -378,9 +905,98 @@ mod tests {
     }
 
     #[test]
-    fn inverse_requires_non_zero_constant_term() {
-        let zero_constant = series_from_coeffs(&[0, 1], 3);
-        assert_eq!(zero_constant.inverse(), Err(EvalError::DivisionByZero));
+    fn inverse_requires_non_zero_coefficient() {
+        let zero = series_from_coeffs(&[0, 0], 3);
+        assert_eq!(zero.inverse(), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn inverse_of_zero_constant_term_is_laurent() {
+        let series = series_from_coeffs(&[0, 0, 1, 1], 3); // x^2 + x^3
+        let inverse = series.inverse().unwrap();
+        assert_eq!(inverse.valuation(), Rational64::from_integer(-2));
+        assert_eq!(format!("{}", inverse), "x^-2 - x^-1 + O(1)");
+        assert_eq!(inverse.mul(&series), Series::one(1));
+    }
+
+    #[test]
+    fn laurent_series_respect_power_series_functions() {
+        let inverse = Series::variable(3).inverse().unwrap();
+        assert_eq!(inverse.exp(), Err(EvalError::NegativePowers("exp")));
+        assert_eq!(
+            inverse.integral(&BigRational::zero()),
+            Err(EvalError::LogarithmicIntegral)
+        );
+
+        let square = inverse.powi(2).unwrap();
+        assert_eq!(
+            format!("{}", square.derivative().unwrap()),
+            "-2 x^-3 + O(1)"
+        );
+        assert_eq!(
+            format!("{}", square.integral(&BigRational::one()).unwrap()),
+            "-x^-1 + 1 + O(x^2)"
+        );
+    }
+
+    #[test]
+    fn fractional_powers_give_puiseux_series() {
+        let x = Series::variable(3);
+        let half = BigRational::new(1.into(), 2.into());
+        let root = x.powr(&half).unwrap();
+        assert_eq!(root.ramification(), 2);
+        assert_eq!(root.valuation(), Rational64::new(1, 2));
+        assert_eq!(root.mul(&root), x);
+
+        let third = BigRational::new(1.into(), 3.into());
+        let series = x.powr(&third).unwrap().mul(&Series::one(3).add(&x));
+        assert_eq!(format!("{}", series), "x^(1/3) + x^(4/3) + O(x^(10/3))");
+        assert_eq!(
+            format!("{}", root.inverse().unwrap()),
+            "x^(-1/2) + O(x^(5/2))"
+        );
+        assert_eq!(
+            format!("{}", root.derivative().unwrap()),
+            "1/2 x^(-1/2) + O(x^(5/2))"
+        );
+        assert_eq!(
+            root.compose(&x),
+            Err(EvalError::FractionalExponents("compose"))
+        );
+    }
+
+    #[test]
+    fn rejects_huge_ramification() {
+        let x = Series::variable(2);
+        let tiny = BigRational::new(1.into(), 1_000_000_000.into());
+        assert_eq!(x.powr(&tiny), Err(EvalError::ExponentTooLarge));
+
+        let a = x.powr(&BigRational::new(1.into(), 997.into())).unwrap();
+        let b = x.powr(&BigRational::new(1.into(), 991.into())).unwrap();
+        assert_eq!(a.check_aligned(&b), Err(EvalError::ExponentTooLarge));
+        assert_eq!(a.div(&b), Err(EvalError::ExponentTooLarge));
+    }
+
+    #[test]
+    fn displays_expansion_point() {
+        let series = series_from_coeffs(&[1, -1, 0, 3], 3); // 1 - t + 3 t^3
+        let point: ExpansionPoint = "-2".parse().unwrap();
+        assert_eq!(
+            format!("{}", series.display_at(&point)),
+            "1 - (x+2) + 3 (x+2)^3 + O((x+2)^4)"
+        );
+
+        let half = series_from_coeffs(&[0, 0, 1], 3).scale(&BigRational::new(1.into(), 2.into()));
+        let infinity: ExpansionPoint = "inf".parse().unwrap();
+        assert_eq!(
+            format!("{}", half.inverse().unwrap().display_at(&infinity)),
+            "2 x^2 + O(1)"
+        );
+        assert_eq!(
+            format!("{}", half.display_at(&infinity)),
+            "1/(2 x^2) + O(1/x^4)"
+        );
+        assert!("x".parse::<ExpansionPoint>().is_err());
     }
 
     #[test]
@@ -398,10 +1014,130 @@ mod tests {
         assert_eq!(inverse, expected);
     }
 
+    #[test]
+    fn composition_substitutes_inner_series() {
+        let geometric = series_from_coeffs(&[1, 1, 1, 1, 1], 4); // 1/(1 - x)
+        let square = series_from_coeffs(&[0, 0, 1], 4); // x^2
+        let composed = geometric.compose(&square).unwrap();
+        assert_eq!(composed, series_from_coeffs(&[1, 0, 1, 0, 1], 4));
+
+        let shifted = series_from_coeffs(&[1, 1], 4);
+        assert_eq!(
+            geometric.compose(&shifted),
+            Err(EvalError::FunctionRequiresZeroConstant("compose"))
+        );
+    }
+
+    #[test]
+    fn reversion_inverts_composition() {
+        let series = series_from_coeffs(&[0, 2, -1, 3, 5], 4);
+        let reverted = series.reversion().unwrap();
+        assert_eq!(series.compose(&reverted).unwrap(), Series::variable(4));
+        assert_eq!(reverted.compose(&series).unwrap(), Series::variable(4));
+        assert_eq!(reverted.reversion().unwrap(), series);
+
+        let no_linear_term = series_from_coeffs(&[0, 0, 1], 4);
+        assert_eq!(no_linear_term.reversion(), Err(EvalError::NotReversible));
+    }
+
+    #[test]
+    fn derivative_and_integral_adjust_order() {
+        let series = series_from_coeffs(&[1, 2, 3, 4], 3);
+        let derivative = series.derivative().unwrap();
+        assert_eq!(format!("{}", derivative), "2 + 6 x + 12 x^2 + O(x^3)");
+
+        let integral = derivative.integral(&BigRational::one()).unwrap();
+        assert_eq!(integral, series);
+
+        let constant = Series::one(0);
+        assert_eq!(format!("{}", constant.derivative().unwrap()), "O(1)");
+        assert_eq!(
+            format!("{}", constant.derivative().unwrap().add(&series)),
+            "O(1)"
+        );
+    }
+
+    #[test]
+    fn arithmetic_keeps_lower_precision() {
+        let short = series_from_coeffs(&[1, 1], 1);
+        let long = series_from_coeffs(&[1, 1, 1, 1], 3);
+        assert_eq!(format!("{}", long.add(&short)), "2 + 2 x + O(x^2)");
+        assert_eq!(format!("{}", long.mul(&short)), "1 + 2 x + O(x^2)");
+
+        let shifted = long.derivative().unwrap().mul(&Series::variable(3));
+        assert_eq!(shifted.order(), Rational64::from_integer(4));
+        assert_eq!(
+            format!("{}", shifted.add(&long)),
+            "1 + 2 x + 3 x^2 + 4 x^3 + O(x^4)"
+        );
+    }
+
     #[test]
     fn zero_series_checks() {
         let zero = Series::zero(2);
         assert!(zero.is_constant());
         assert!(zero.constant_term().is_zero());
     }
+
+    /// `sum_n c(n) g^n` by repeated multiplication, as the recurrences
+    /// replaced.
+    fn taylor_sum(g: &Series, c: impl Fn(usize) -> BigRational) -> Series {
+        let mut result = g.one_like().scale(&c(0));
+        for n in 1..g.coeffs.len() {
+            result = result.add(&g.powi(n as i64).unwrap().scale(&c(n)));
+        }
+        result
+    }
+
+    fn inverse_factorial(n: usize) -> BigRational {
+        let factorial: BigInt = (1..=n).map(BigInt::from).product();
+        BigRational::new(BigInt::one(), factorial)
+    }
+
+    fn binomial(e: &BigRational, n: usize) -> BigRational {
+        (0..n).fold(BigRational::one(), |b, k| {
+            b * (e - BigInt::from(k)) / BigInt::from(k + 1)
+        })
+    }
+
+    #[test]
+    fn recurrences_match_taylor_sums() {
+        let half = BigRational::new(1.into(), 2.into());
+        let mut g = series_from_coeffs(&[0, 1, -3, 0, 0, 2, 0, -1], 12);
+        let sqrt = Series::variable(12).powr(&half).unwrap();
+        for g in [g.clone(), sqrt.add(&g.ramified(2)), Series::zero(0)] {
+            let sign = |n: usize| if n % 4 < 2 { 1 } else { -1 };
+            let sin = taylor_sum(&g, |n| match n % 2 {
+                1 => inverse_factorial(n) * BigInt::from(sign(n)),
+                _ => BigRational::zero(),
+            });
+            let cos = taylor_sum(&g, |n| match n % 2 {
+                0 => inverse_factorial(n) * BigInt::from(sign(n)),
+                _ => BigRational::zero(),
+            });
+            let log = taylor_sum(&g, |n| match n {
+                0 => BigRational::zero(),
+                n => BigRational::new(BigInt::from(if n % 2 == 1 { 1 } else { -1 }), n.into()),
+            });
+            let power = BigRational::new((-7).into(), 3.into());
+
+            assert_eq!(g.exp().unwrap(), taylor_sum(&g, inverse_factorial));
+            assert_eq!(g.sin().unwrap(), sin);
+            assert_eq!(g.cos().unwrap(), cos);
+            let one = g.one_like();
+            assert_eq!(one.add(&g).log().unwrap(), log);
+            assert_eq!(
+                one.add(&g).powr(&power).unwrap(),
+                taylor_sum(&g, |n| binomial(&power, n))
+            );
+            assert_eq!(
+                one.sub(&g).powr(&half).unwrap(),
+                taylor_sum(&g.neg(), |n| binomial(&half, n))
+            );
+        }
+
+        g.coeffs[0] = BigRational::one();
+        assert_eq!(g.exp(), Err(EvalError::FunctionRequiresZeroConstant("exp")));
+        assert_eq!(g.log().unwrap().exp().unwrap(), g);
+    }
 }
@@ -1,111 +1,263 @@
+use crate::diagnostic::{Diagnostic, Span};
+use num_bigint::BigInt;
 use num_rational::BigRational;
+use num_traits::{One, Pow, Zero};
+use std::fmt;
 use thiserror::Error;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum FunctionToken {
-    Sin,
-    Cos,
-    Exp,
-    Log,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Token {
+pub enum TokenKind {
     Num(BigRational),
-    Variable(char),
-    Function(FunctionToken),
+    Ident(String),
+    Let,
     Plus,
     Minus,
     Star,
     Slash,
     Caret,
+    Bang,
     LParen,
     RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Equals,
+    Semicolon,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, span: Span) -> Self {
+        Self { kind, span }
+    }
 }
 
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum TokenizerError {
     #[error("Unexpected character: {0}")]
-    UnexpectedChar(char),
-    #[error("Unexpected identifier: {0}")]
-    UnexpectedIdentifier(String),
+    UnexpectedChar(char, Span),
+    #[error("Invalid number literal: {0}")]
+    InvalidNumber(String, Span),
+}
+
+impl Diagnostic for TokenizerError {
+    fn span(&self) -> Span {
+        match self {
+            TokenizerError::UnexpectedChar(_, span) | TokenizerError::InvalidNumber(_, span) => {
+                *span
+            }
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Num(n) => write!(f, "{}", n),
+            TokenKind::Ident(name) => write!(f, "{}", name),
+            TokenKind::Let => write!(f, "let"),
+            TokenKind::Plus => write!(f, "+"),
+            TokenKind::Minus => write!(f, "-"),
+            TokenKind::Star => write!(f, "*"),
+            TokenKind::Slash => write!(f, "/"),
+            TokenKind::Caret => write!(f, "^"),
+            TokenKind::Bang => write!(f, "!"),
+            TokenKind::LParen => write!(f, "("),
+            TokenKind::RParen => write!(f, ")"),
+            TokenKind::LBracket => write!(f, "["),
+            TokenKind::RBracket => write!(f, "]"),
+            TokenKind::Comma => write!(f, ","),
+            TokenKind::Equals => write!(f, "="),
+            TokenKind::Semicolon => write!(f, ";"),
+        }
+    }
+}
+
+/// Character cursor over the input that keeps track of byte offsets.
+struct Cursor<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str) -> Self {
+        Cursor { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(start, self.pos)
+    }
+
+    fn slice_from(&self, start: usize) -> &'a str {
+        &self.input[start..self.pos]
+    }
 }
 
 pub fn tokenize(input: &str) -> Result<Vec<Token>, TokenizerError> {
     let mut tokens = Vec::new();
-    let mut chars = input.chars().peekable();
-
-    while let Some(&c) = chars.peek() {
-        match c {
-            '0'..='9' => {
-                let mut num_str = String::new();
-                while let Some(&d) = chars.peek() {
-                    if d.is_digit(10) {
-                        num_str.push(d);
-                        chars.next();
-                    } else {
-                        break;
-                    }
-                }
-                let num = BigRational::from_integer(num_str.parse().unwrap());
-                tokens.push(Token::Num(num));
-            }
+    let mut cursor = Cursor::new(input);
+
+    while let Some(c) = cursor.peek() {
+        let start = cursor.pos;
+        let kind = match c {
+            '0'..='9' | '.' => TokenKind::Num(read_number(&mut cursor)?),
             'a'..='z' | 'A'..='Z' => {
-                let mut ident = String::new();
-                while let Some(&d) = chars.peek() {
-                    if d.is_alphabetic() {
-                        ident.push(d);
-                        chars.next();
-                    } else {
-                        break;
-                    }
+                while cursor.peek().is_some_and(|d| d.is_alphabetic()) {
+                    cursor.bump();
+                }
+                match cursor.slice_from(start) {
+                    "let" => TokenKind::Let,
+                    ident => TokenKind::Ident(ident.to_string()),
                 }
-                let token = match ident.as_str() {
-                    "x" => Token::Variable('x'),
-                    "sin" => Token::Function(FunctionToken::Sin),
-                    "cos" => Token::Function(FunctionToken::Cos),
-                    "exp" => Token::Function(FunctionToken::Exp),
-                    "log" => Token::Function(FunctionToken::Log),
-                    _ => return Err(TokenizerError::UnexpectedIdentifier(ident)),
-                };
-                tokens.push(token);
-            }
-            '+' => {
-                tokens.push(Token::Plus);
-                chars.next();
-            }
-            '-' => {
-                tokens.push(Token::Minus);
-                chars.next();
-            }
-            '*' => {
-                tokens.push(Token::Star);
-                chars.next();
-            }
-            '/' => {
-                tokens.push(Token::Slash);
-                chars.next();
-            }
-            '^' => {
-                tokens.push(Token::Caret);
-                chars.next();
             }
-            '(' => {
-                tokens.push(Token::LParen);
-                chars.next();
+            c if c.is_whitespace() => {
+                cursor.bump();
+                continue;
             }
-            ')' => {
-                tokens.push(Token::RParen);
-                chars.next();
+            _ => {
+                cursor.bump();
+                match c {
+                    '+' => TokenKind::Plus,
+                    '-' => TokenKind::Minus,
+                    '*' => TokenKind::Star,
+                    '/' => TokenKind::Slash,
+                    '^' => TokenKind::Caret,
+                    '!' => TokenKind::Bang,
+                    '(' => TokenKind::LParen,
+                    ')' => TokenKind::RParen,
+                    '[' => TokenKind::LBracket,
+                    ']' => TokenKind::RBracket,
+                    ',' => TokenKind::Comma,
+                    '=' => TokenKind::Equals,
+                    ';' => TokenKind::Semicolon,
+                    _ => {
+                        return Err(TokenizerError::UnexpectedChar(c, cursor.span_from(start)));
+                    }
+                }
             }
-            c if c.is_whitespace() => {
-                chars.next();
+        };
+        tokens.push(Token::new(kind, cursor.span_from(start)));
+    }
+
+    Ok(insert_implicit_stars(tokens))
+}
+
+const MAX_DECIMAL_EXPONENT: u32 = 10_000;
+
+/// Reads a numeric literal and converts it to an exact rational.
+///
+/// Accepts integers (`12`), decimals (`0.25`, `1.` and `.5`), a repeating
+/// decimal written right after the point (`0.(3)` is 1/3) and a decimal
+/// exponent (`1.5e-3`). An `e` that does not start a longer name, such as
+/// `exp`, must be followed by exponent digits. Any other parenthesis after a
+/// decimal is left to the parser, so `0.5(2)` and `0.(1+x)` are implicit
+/// products.
+fn read_number(cursor: &mut Cursor<'_>) -> Result<BigRational, TokenizerError> {
+    let start = cursor.pos;
+    let invalid = |cursor: &Cursor<'_>| {
+        TokenizerError::InvalidNumber(
+            cursor.slice_from(start).to_string(),
+            cursor.span_from(start),
+        )
+    };
+
+    let integer_part = read_digits(cursor);
+    let mut fraction_part = "";
+    let mut repeating_part = "";
+
+    if cursor.peek() == Some('.') {
+        cursor.bump();
+        fraction_part = read_digits(cursor);
+
+        if fraction_part.is_empty() && cursor.peek() == Some('(') {
+            let digits = (1..)
+                .take_while(|&n| cursor.peek_nth(n).is_some_and(|c| c.is_ascii_digit()))
+                .count();
+            if digits > 0 && cursor.peek_nth(digits + 1) == Some(')') {
+                cursor.bump();
+                repeating_part = read_digits(cursor);
+                cursor.bump();
             }
-            _ => return Err(TokenizerError::UnexpectedChar(c)),
+        }
+        if integer_part.is_empty() && fraction_part.is_empty() && repeating_part.is_empty() {
+            return Err(invalid(cursor));
         }
     }
 
-    Ok(insert_implicit_stars(tokens))
+    let mut exponent: i32 = 0;
+    if matches!(cursor.peek(), Some('e' | 'E'))
+        && !cursor.peek_nth(1).is_some_and(|c| c.is_alphabetic())
+    {
+        cursor.bump();
+        let sign = cursor.peek().filter(|c| matches!(c, '+' | '-'));
+        if sign.is_some() {
+            cursor.bump();
+        }
+        let digits = read_digits(cursor);
+        exponent = digits.parse().map_err(|_| invalid(cursor))?;
+        if exponent.unsigned_abs() > MAX_DECIMAL_EXPONENT {
+            return Err(invalid(cursor));
+        }
+        if sign == Some('-') {
+            exponent = -exponent;
+        }
+    }
+
+    let ten = BigInt::from(10);
+    let parse_digits = |digits: &str| -> BigInt {
+        if digits.is_empty() {
+            BigInt::zero()
+        } else {
+            digits.parse().unwrap()
+        }
+    };
+
+    let fraction_scale = Pow::pow(&ten, fraction_part.len());
+    let mut value = BigRational::new(
+        parse_digits(&format!("{integer_part}{fraction_part}")),
+        fraction_scale.clone(),
+    );
+
+    if !repeating_part.is_empty() {
+        let period = Pow::pow(&ten, repeating_part.len()) - BigInt::one();
+        value += BigRational::new(parse_digits(repeating_part), fraction_scale * period);
+    }
+
+    let scale = BigRational::from_integer(Pow::pow(&ten, exponent.unsigned_abs()));
+    if exponent >= 0 {
+        value *= scale;
+    } else {
+        value /= scale;
+    }
+
+    Ok(value)
+}
+
+fn read_digits<'a>(cursor: &mut Cursor<'a>) -> &'a str {
+    let start = cursor.pos;
+    while cursor.peek().is_some_and(|d| d.is_ascii_digit()) {
+        cursor.bump();
+    }
+    cursor.slice_from(start)
 }
 
 fn insert_implicit_stars(tokens: Vec<Token>) -> Vec<Token> {
@@ -120,17 +272,192 @@ fn insert_implicit_stars(tokens: Vec<Token>) -> Vec<Token> {
         let prev = &tokens[i - 1];
         let curr = &tokens[i];
 
-        let prev_is_value = matches!(prev, Token::Num(_) | Token::Variable(_) | Token::RParen);
+        let prev_is_value = matches!(
+            prev.kind,
+            TokenKind::Num(_) | TokenKind::Ident(_) | TokenKind::RParen | TokenKind::Bang
+        );
         let curr_starts_value = matches!(
-            curr,
-            Token::Num(_) | Token::Variable(_) | Token::LParen | Token::Function(_)
+            curr.kind,
+            TokenKind::Num(_) | TokenKind::Ident(_) | TokenKind::LParen
         );
+        // `f(` may be a function call, which only the parser can tell apart
+        // from a product.
+        let may_be_call =
+            matches!(prev.kind, TokenKind::Ident(_)) && matches!(curr.kind, TokenKind::LParen);
 
-        if prev_is_value && curr_starts_value {
-            new_tokens.push(Token::Star);
+        if prev_is_value && curr_starts_value && !may_be_call {
+            new_tokens.push(Token::new(TokenKind::Star, Span::point(curr.span.start)));
         }
         new_tokens.push(curr.clone());
     }
 
     new_tokens
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rational(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(numer.into(), denom.into())
+    }
+
+    fn single_number(input: &str) -> BigRational {
+        match tokenize(input).expect("tokenize").as_slice() {
+            [
+                Token {
+                    kind: TokenKind::Num(n),
+                    ..
+                },
+            ] => n.clone(),
+            other => panic!("expected a single number, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decimals_are_exact() {
+        assert_eq!(single_number("0.25"), rational(1, 4));
+        assert_eq!(single_number("12.5"), rational(25, 2));
+        assert_eq!(single_number("3.000"), rational(3, 1));
+    }
+
+    #[test]
+    fn scientific_notation() {
+        assert_eq!(single_number("1.5e-3"), rational(3, 2000));
+        assert_eq!(single_number("2E3"), rational(2000, 1));
+        assert_eq!(single_number("25e+1"), rational(250, 1));
+    }
+
+    #[test]
+    fn repeating_decimals() {
+        assert_eq!(single_number("0.(3)"), rational(1, 3));
+        assert_eq!(single_number("0.(16)"), rational(16, 99));
+        assert_eq!(single_number("1.(142857)"), rational(8, 7));
+        assert_eq!(single_number("0.(3)e1"), rational(10, 3));
+    }
+
+    #[test]
+    fn parenthesis_after_decimal_is_a_product() {
+        let kinds: Vec<TokenKind> = tokenize("0.5(2)")
+            .expect("tokenize")
+            .into_iter()
+            .map(|t| t.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Num(rational(1, 2)),
+                TokenKind::Star,
+                TokenKind::LParen,
+                TokenKind::Num(rational(2, 1)),
+                TokenKind::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn exponent_marker_needs_digits() {
+        let tokens = tokenize("2exp(x)").expect("tokenize");
+        assert_eq!(tokens[0].kind, TokenKind::Num(rational(2, 1)));
+        assert_eq!(tokens[1].kind, TokenKind::Star);
+        assert_eq!(tokens[2].kind, TokenKind::Ident("exp".to_string()));
+        assert_eq!(tokens[2].span, Span::new(1, 4));
+    }
+
+    #[test]
+    fn decimal_point_needs_digits_on_one_side() {
+        assert_eq!(single_number("1."), rational(1, 1));
+        assert_eq!(single_number(".5"), rational(1, 2));
+        assert_eq!(single_number(".(3)"), rational(1, 3));
+        assert_eq!(single_number("2.e2"), rational(200, 1));
+        assert_eq!(
+            tokenize("."),
+            Err(TokenizerError::InvalidNumber(
+                ".".to_string(),
+                Span::new(0, 1)
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_exponent_without_digits() {
+        assert_eq!(
+            tokenize("1e"),
+            Err(TokenizerError::InvalidNumber(
+                "1e".to_string(),
+                Span::new(0, 2)
+            ))
+        );
+        assert_eq!(
+            tokenize("2.5e-x"),
+            Err(TokenizerError::InvalidNumber(
+                "2.5e-".to_string(),
+                Span::new(0, 5)
+            ))
+        );
+    }
+
+    #[test]
+    fn parenthesis_that_is_not_a_repeating_part_is_a_product() {
+        for input in ["0.(3", "0.(3x)", "0.(1+x)"] {
+            let tokens = tokenize(input).expect("tokenize");
+            assert_eq!(tokens[0].kind, TokenKind::Num(rational(0, 1)), "{input}");
+            assert_eq!(tokens[1].kind, TokenKind::Star, "{input}");
+            assert_eq!(tokens[2].kind, TokenKind::LParen, "{input}");
+        }
+    }
+
+    #[test]
+    fn tokens_carry_byte_spans() {
+        let tokens = tokenize("12 + sin(x)").expect("tokenize");
+        let spans: Vec<Span> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 2),
+                Span::new(3, 4),
+                Span::new(5, 8),
+                Span::new(8, 9),
+                Span::new(9, 10),
+                Span::new(10, 11),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_point_at_offending_input() {
+        assert_eq!(
+            tokenize("1 + é"),
+            Err(TokenizerError::UnexpectedChar('é', Span::new(4, 6)))
+        );
+        assert_eq!(
+            tokenize("2 ~ 3"),
+            Err(TokenizerError::UnexpectedChar('~', Span::new(2, 3)))
+        );
+    }
+
+    #[test]
+    fn identifiers_and_bindings() {
+        let kinds: Vec<TokenKind> = tokenize("let g = 2q; f(t)")
+            .expect("tokenize")
+            .into_iter()
+            .map(|t| t.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Let,
+                TokenKind::Ident("g".to_string()),
+                TokenKind::Equals,
+                TokenKind::Num(rational(2, 1)),
+                TokenKind::Star,
+                TokenKind::Ident("q".to_string()),
+                TokenKind::Semicolon,
+                TokenKind::Ident("f".to_string()),
+                TokenKind::LParen,
+                TokenKind::Ident("t".to_string()),
+                TokenKind::RParen,
+            ]
+        );
+    }
+}
@@ -1,46 +1,38 @@
 use yew::prelude::*;
 
-// TODO: map_errを使ってキレイに書き直す(FPS計算がフロントエンドで動くようになったら)
-fn calc_fps(expression: &str, max_deg: usize) -> Option<String> {
-    let tokens = fps_core::tokenizer::tokenize(expression);
-
-    if let Err(e) = tokens {
-        web_sys::console::log_1(&format!("Tokenization error: {:?}", e).into());
-        return None;
-    }
-    let tokens: Vec<fps_core::tokenizer::Token> = tokens.unwrap();
-    let expr = fps_core::parser::parse(&tokens);
-
-    if let Err(e) = expr {
-        web_sys::console::log_1(&format!("Parsing error: {:?}", e).into());
-        return None;
-    }
-    let expr = expr.unwrap();
-
-    let series = fps_core::evaluator::evaluate(&expr, max_deg);
-
-    if let Err(e) = series {
-        web_sys::console::log_1(&format!("Evaluation error: {:?}", e).into());
-        return None;
-    }
-    let series = series.unwrap();
-
-    Some(format!("{}", series))
+use fps_core::functions::FunctionRegistry;
+use fps_core::query::{self, Options};
+use fps_core::series::Series;
+
+/// Prime offered when modular arithmetic is switched on.
+const DEFAULT_MODULUS: u64 = 998244353;
+
+fn calc_fps(expression: &str, max_deg: usize, modulus: Option<u64>) -> Result<String, String> {
+    let options = Options {
+        max_degree: max_deg,
+        modulus,
+        ..Options::default()
+    };
+    query::run(expression, &options).map_err(|error| {
+        web_sys::console::log_1(&format!("Evaluation error: {}", error).into());
+        error.render(expression)
+    })
 }
 
 #[function_component]
 fn App() -> Html {
     let expression: UseStateHandle<String> = use_state(|| String::from("1/(1-x)"));
     let max_degree: UseStateHandle<usize> = use_state(|| 5);
+    let modulus: UseStateHandle<Option<u64>> = use_state(|| None);
     let result: UseStateHandle<String> = use_state(String::new);
 
     {
         let result = result.clone();
         use_effect_with(
-            ((*expression).clone(), *max_degree),
-            move |(expr_value, deg_value): &(String, usize)| {
-                let computed = calc_fps(expr_value, *deg_value)
-                    .unwrap_or_else(|| "Unable to evaluate expression".to_string());
+            ((*expression).clone(), *max_degree, *modulus),
+            move |(expr_value, deg_value, modulus_value): &(String, usize, Option<u64>)| {
+                let computed =
+                    calc_fps(expr_value, *deg_value, *modulus_value).unwrap_or_else(|error| error);
                 result.set(computed);
                 || ()
             },
@@ -68,12 +60,46 @@ fn App() -> Html {
         })
     };
 
+    let on_modular_toggle: Callback<Event> = {
+        let modulus = modulus.clone();
+        Callback::from(move |event: Event| {
+            let input: HtmlInputElement = event.target_unchecked_into::<HtmlInputElement>();
+            modulus.set(input.checked().then_some(DEFAULT_MODULUS));
+            web_sys::console::log_1(&format!("Modular arithmetic: {}", input.checked()).into());
+        })
+    };
+
+    let on_modulus_change: Callback<InputEvent> = {
+        let modulus = modulus.clone();
+        Callback::from(move |event: InputEvent| {
+            let input: HtmlInputElement = event.target_unchecked_into::<HtmlInputElement>();
+            let parsed = input.value().parse::<u64>().unwrap_or(DEFAULT_MODULUS);
+            modulus.set(Some(parsed));
+            web_sys::console::log_1(&format!("Modulus changed: {}", parsed).into());
+        })
+    };
+
+    let function_names = FunctionRegistry::<Series>::standard()
+        .names()
+        .collect::<Vec<_>>()
+        .join(", ");
+    let syntax_hint = format!(
+        "Supports +, -, *, /, ^, !, {}, [x^n] f, let bindings and user functions like f(t) = 1/(1-t); f(x^2).",
+        function_names
+    );
+
     let preset_expressions = vec![
         ("Geometric Series", "1/(1-x)"),
         ("Exponential", "exp(x)"),
         ("Sine", "sin(x)"),
         ("Cosine", "cos(x)"),
         ("Logarithm", "log(1+x)"),
+        ("Bivariate", "1/(1-x-x*y)"),
+        ("Let binding", "let g = 1/(1-x-x^2); g^2 + g"),
+        ("Tree function", "revert(x*exp(-x))"),
+        ("Coefficient", "[x^20] 1/(1-x-x^2)"),
+        ("Puiseux", "sqrt(x + x^2)"),
+        ("Factor", "factor(x^6 - 1)"),
     ];
 
     let preset_buttons = preset_expressions.into_iter().map(|(label, value)| {
@@ -117,7 +143,7 @@ fn App() -> Html {
                                 placeholder="e.g. 1/(1-x)"
                             />
                         </div>
-                        <p class="hint">{"Supports +, -, *, /, sin, cos, exp, log, and custom fps_core functions."}</p>
+                        <p class="hint">{syntax_hint}</p>
                     </div>
 
                     <div class="field">
@@ -136,6 +162,30 @@ fn App() -> Html {
                         </div>
                         <p class="hint">{"Clamp stays between 1 and 32 to keep evaluation responsive."}</p>
                     </div>
+
+                    <div class="field">
+                        <label for="modulus">
+                            <input
+                                type="checkbox"
+                                checked={modulus.is_some()}
+                                onchange={on_modular_toggle.clone()}
+                            />
+                            {" Modulo a prime"}
+                        </label>
+                        <div class="input-wrapper number">
+                            <input
+                                id="modulus"
+                                class="text-input"
+                                type="number"
+                                min="2"
+                                disabled={modulus.is_none()}
+                                value={modulus.unwrap_or(DEFAULT_MODULUS).to_string()}
+                                oninput={on_modulus_change.clone()}
+                            />
+                            <span class="suffix">{"p"}</span>
+                        </div>
+                        <p class="hint">{"Coefficients become residues modulo a prime below 2^32."}</p>
+                    </div>
                 </section>
 
                 <section class="preset-panel">
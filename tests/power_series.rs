@@ -1,4 +1,12 @@
+use fps_core::coefficient::{Coefficient, Field};
+use fps_core::evaluator::EvalError;
+use fps_core::functions::FunctionRegistry;
+use fps_core::series::ExpansionPoint;
 use fps_core::{evaluator, parser, tokenizer};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use std::fmt;
 
 fn evaluate(expr: &str, max_degree: usize) -> String {
     let tokens = tokenizer::tokenize(expr).expect("tokenize");
@@ -7,6 +15,14 @@ fn evaluate(expr: &str, max_degree: usize) -> String {
     format!("{}", series)
 }
 
+fn evaluate_at(expr: &str, point: &str, max_degree: usize) -> String {
+    let point: ExpansionPoint = point.parse().expect("point");
+    let tokens = tokenizer::tokenize(expr).expect("tokenize");
+    let ast = parser::parse(&tokens).expect("parse");
+    let series = evaluator::evaluate_at(&ast, &point, max_degree).expect("evaluate");
+    format!("{}", series.display_at(&point))
+}
+
 #[test]
 fn geometric_series_matches_expected_output() {
     let formatted = evaluate("1 / (1 - x)", 3);
@@ -36,3 +52,240 @@ fn log_series_matches_expected_output() {
     let formatted = evaluate("log(1 + 7x)", 3);
     assert_eq!(formatted, "7 x - 49/2 x^2 + 343/3 x^3 + O(x^4)");
 }
+
+#[test]
+fn factorial_example_from_design_memo() {
+    let formatted = evaluate("2exp(3x/(2!)) / ((1-x)^3)", 2);
+    assert_eq!(formatted, "2 + 9 x + 93/4 x^2 + O(x^3)");
+}
+
+#[test]
+fn decimal_literals_are_exact_rationals() {
+    let formatted = evaluate("0.25x + 1.5e-3 / (1 - 0.(3)x)", 2);
+    assert_eq!(formatted, "3/2000 + 501/2000 x + 1/6000 x^2 + O(x^3)");
+}
+
+#[test]
+fn parenthesis_after_decimal_multiplies() {
+    assert_eq!(evaluate("0.5(1+x)", 2), "1/2 + 1/2 x + O(x^3)");
+    assert_eq!(evaluate("0.5(2)", 2), "1 + O(x^3)");
+}
+
+#[test]
+fn user_functions_expand_products() {
+    let formatted = evaluate("f(t) = 1/(1-t); f(q)f(q^2)", 4);
+    assert_eq!(formatted, "1 + q + 2 q^2 + 2 q^3 + 3 q^4 + O(q^5)");
+}
+
+#[test]
+fn lambert_tree_function_by_reversion() {
+    let formatted = evaluate("revert(x*exp(-x))", 5);
+    assert_eq!(
+        formatted,
+        "x + x^2 + 3/2 x^3 + 8/3 x^4 + 125/24 x^5 + O(x^6)"
+    );
+}
+
+#[test]
+fn composition_substitutes_series() {
+    let formatted = evaluate("compose(exp(x), sin(x))", 4);
+    assert_eq!(formatted, "1 + x + 1/2 x^2 - 1/8 x^4 + O(x^5)");
+}
+
+#[test]
+fn derivative_keeps_requested_order() {
+    let formatted = evaluate("D(1/(1-x))", 3);
+    assert_eq!(formatted, "1 + 2 x + 3 x^2 + 4 x^3 + O(x^4)");
+}
+
+#[test]
+fn integral_is_truncated_to_requested_order() {
+    let formatted = evaluate("int(1/(1+x^2))", 3);
+    assert_eq!(formatted, "x - 1/3 x^3 + O(x^4)");
+}
+
+#[test]
+fn integral_takes_optional_constant() {
+    let formatted = evaluate("int(cos(x), 2)", 3);
+    assert_eq!(formatted, "2 + x - 1/6 x^3 + O(x^4)");
+
+    let formatted = evaluate_at("int(1/x, 0)", "1", 2);
+    assert_eq!(formatted, "(x-1) - 1/2 (x-1)^2 + O((x-1)^3)");
+
+    let tokens = tokenizer::tokenize("int(x, x)").expect("tokenize");
+    let ast = parser::parse(&tokens).expect("parse");
+    let error = evaluator::evaluate(&ast, 3).unwrap_err();
+    assert_eq!(
+        error.error,
+        evaluator::EvalError::NonConstantIntegrationConstant
+    );
+}
+
+#[test]
+fn coefficient_extraction_ignores_max_degree() {
+    let formatted = evaluate("[x^6] revert(x*exp(-x)) + x", 2);
+    assert_eq!(formatted, "54/5 + x + O(x^3)");
+}
+
+#[test]
+fn square_root_uses_generalized_binomial_series() {
+    let formatted = evaluate("(1 + x)^(1/2)", 4);
+    assert_eq!(
+        formatted,
+        "1 + 1/2 x - 1/8 x^2 + 1/16 x^3 - 5/128 x^4 + O(x^5)"
+    );
+}
+
+#[test]
+fn series_exponent_expands_through_exp_and_log() {
+    let formatted = evaluate("(1 + x)^sin(x)", 3);
+    assert_eq!(formatted, "1 + x^2 - 1/2 x^3 + O(x^4)");
+}
+
+#[test]
+fn laurent_series_show_negative_powers() {
+    assert_eq!(evaluate("1/x", 3), "x^-1 + O(x^4)");
+    assert_eq!(evaluate("sin(x)/x^3", 3), "x^-2 - 1/6 + 1/120 x^2 + O(x^4)");
+    assert_eq!(evaluate("1/x - 1/x", 3), "0 + O(x^4)");
+}
+
+#[test]
+fn positive_valuations_do_not_extend_requested_order() {
+    assert_eq!(evaluate("exp(x^2 sin(x))", 3), "1 + x^3 + O(x^4)");
+    assert_eq!(evaluate("x^3 log(1 + x)", 3), "0 + O(x^4)");
+}
+
+#[test]
+fn division_by_powers_of_x_keeps_requested_order() {
+    let formatted = evaluate("sin(x)/x", 3);
+    assert_eq!(formatted, "1 - 1/6 x^2 + O(x^4)");
+
+    let formatted = evaluate("x/(exp(x) - 1)", 4);
+    assert_eq!(formatted, "1 - 1/2 x + 1/12 x^2 - 1/720 x^4 + O(x^5)");
+}
+
+#[test]
+fn fractional_powers_give_puiseux_series() {
+    assert_eq!(evaluate("sqrt(x)", 3), "x^(1/2) + O(x^4)");
+    assert_eq!(evaluate("x^(1/3)*(1+x)", 3), "x^(1/3) + x^(4/3) + O(x^4)");
+    assert_eq!(
+        evaluate("sqrt(x + x^2)", 2),
+        "x^(1/2) + 1/2 x^(3/2) - 1/8 x^(5/2) + O(x^3)"
+    );
+}
+
+#[test]
+fn expansion_around_finite_point_and_infinity() {
+    assert_eq!(
+        evaluate_at("log(x)", "1", 3),
+        "(x-1) - 1/2 (x-1)^2 + 1/3 (x-1)^3 + O((x-1)^4)"
+    );
+    assert_eq!(
+        evaluate_at("1/(x^2 - 1)", "1", 1),
+        "1/2 (x-1)^-1 - 1/4 + 1/8 (x-1) + O((x-1)^2)"
+    );
+    assert_eq!(
+        evaluate_at("x/(x^2 + 1)", "inf", 5),
+        "1/x - 1/x^3 + 1/x^5 + O(1/x^6)"
+    );
+    assert_eq!(
+        evaluate_at("exp(1/x)", "inf", 2),
+        "1 + 1/x + 1/(2 x^2) + O(1/x^3)"
+    );
+    assert_eq!(evaluate_at("[x^-1] x/(x^2 + 1)", "inf", 0), "1 + O(1/x^1)");
+}
+
+/// Integers modulo 5, a field defined outside the crate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Gf5(u8);
+
+impl fmt::Display for Gf5 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Coefficient for Gf5 {
+    type Context = ();
+
+    fn from_integer(value: &BigInt, _context: &()) -> Self {
+        let residue = value % BigInt::from(5);
+        Gf5(residue.to_i64().unwrap().rem_euclid(5) as u8)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    fn is_one(&self) -> bool {
+        self.0 == 1
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Gf5((self.0 + other.0) % 5)
+    }
+
+    fn neg(&self) -> Self {
+        Gf5((5 - self.0) % 5)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        Gf5(self.0 * other.0 % 5)
+    }
+
+    /// Like a residue modulo a large prime, an element of GF(5) does not
+    /// stand for a particular rational.
+    fn to_rational(&self) -> Option<BigRational> {
+        None
+    }
+}
+
+impl Field for Gf5 {
+    fn from_rational(value: &BigRational, context: &()) -> Result<Self, EvalError> {
+        let denom = Gf5::from_integer(value.denom(), context).inverse()?;
+        Ok(Gf5::from_integer(value.numer(), context).mul(&denom))
+    }
+
+    fn inverse(&self) -> Result<Self, EvalError> {
+        match self.0 {
+            0 => Err(EvalError::Custom("0 has no inverse in GF(5)".to_string())),
+            a => Ok(Gf5([0, 1, 3, 2, 4][a as usize])),
+        }
+    }
+
+    fn powr(&self, _exponent: &BigRational) -> Result<Self, EvalError> {
+        Err(EvalError::Custom("no roots in GF(5)".to_string()))
+    }
+}
+
+#[test]
+fn evaluates_over_a_user_defined_field() {
+    let evaluate = |expr: &str, max_degree: usize| {
+        let tokens = tokenizer::tokenize(expr).expect("tokenize");
+        let ast = parser::parse(&tokens).expect("parse");
+        evaluator::evaluate_over::<Gf5>(&ast, 'x', &(), max_degree, &FunctionRegistry::standard())
+            .map(|series| format!("{}", series))
+            .map_err(|e| e.to_string())
+    };
+
+    assert_eq!(
+        evaluate("1/(1-x-x^2)", 7).unwrap(),
+        "1 + x + 2 x^2 + 3 x^3 + 3 x^5 + 3 x^6 + x^7 + O(x^8)"
+    );
+    assert_eq!(
+        evaluate("exp(x)", 3).unwrap(),
+        "1 + x + 3 x^2 + x^3 + O(x^4)"
+    );
+    assert_eq!(
+        evaluate("exp(x)", 5).unwrap_err(),
+        "0 has no inverse in GF(5)"
+    );
+
+    // Exponents must be exact, and a coefficient extracted from a series
+    // over GF(5) has no rational value to use.
+    assert_eq!(Gf5(3).to_rational(), None);
+    assert_eq!(
+        evaluate("(1+x)^([x^1] 3x)", 2).unwrap_err(),
+        "Coefficient 3 has no rational value"
+    );
+}
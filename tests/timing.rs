@@ -0,0 +1,52 @@
+//! Measures the running times quoted in the `--maxdeg` help of fps-cli and
+//! in fps-web/README.md. Wall-clock times depend on the machine and its
+//! load, so these only print them; run them on a release build with
+//! `cargo test --release --test timing -- --ignored --nocapture`.
+
+use fps_core::query::{self, Options};
+use std::time::Instant;
+
+fn report(expression: &str, options: &Options) {
+    let start = Instant::now();
+    query::run(expression, options).expect("evaluate");
+    println!(
+        "{expression} to degree {}: {:?}",
+        options.max_degree,
+        start.elapsed()
+    );
+}
+
+fn degree(max_degree: usize) -> Options {
+    Options {
+        max_degree,
+        ..Options::default()
+    }
+}
+
+#[test]
+#[ignore = "prints timings of release builds"]
+fn transcendental_rational_expansions() {
+    for max_degree in [500, 1000] {
+        for expression in ["exp(x)", "sin(x)/cos(x)", "exp(sin(x))"] {
+            report(expression, &degree(max_degree));
+        }
+    }
+}
+
+#[test]
+#[ignore = "prints timings of release builds"]
+fn rational_functions_to_degree_10000() {
+    report("1/(1-x-x^2)", &degree(10_000));
+}
+
+#[test]
+#[ignore = "prints timings of release builds"]
+fn modular_expansions_to_degree_10000() {
+    let options = Options {
+        modulus: Some(998_244_353),
+        ..degree(10_000)
+    };
+    for expression in ["exp(x)", "exp(sin(x))/(1-x)", "log(1+x+x^2)"] {
+        report(expression, &options);
+    }
+}